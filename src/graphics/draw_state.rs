@@ -1,9 +1,12 @@
-use std::collections::HashMap;
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
 
 use wgpu::Device;
 use winit::dpi::PhysicalSize;
 
-use crate::logic::{Asset, Position};
+use crate::logic::{Asset, CameraView, Position};
 
 use super::{
     model::Model, texture::TextureIdentifier, uniforms::DefaultUniforms, vertex_array::VertexArray,
@@ -20,47 +23,117 @@ pub struct DrawPackage {
 /// The state sent from the logic system so the graphics knows what to draw.
 /// Contains all the business logic to convert that data to rendering (for now)
 ///
+/// Holds both the previous and the latest tick, so it can be drawn interpolated between them
+/// at whatever rate the window refreshes.
+///
 pub struct DrawState {
-    entities: Vec<(Asset, Position)>,
-    camera_offset: [f32; 2],
-    time: f32,
+    /// Every entity to draw, with its position in the previous and the latest tick.
+    entities: Vec<(Asset, Position, Position)>,
+    /// Where the camera is centered in the previous and the latest tick.
+    camera_positions: [[f32; 2]; 2],
+    /// How far the camera is shaken off its position, in pixels.
+    shake: [f32; 2],
+    /// The camera's bounds and zoom.
+    camera_view: CameraView,
+    /// The game time of the previous and the latest tick.
+    times: [f32; 2],
+    /// When the latest tick should be shown, and how long it is until the next one.
+    tick_instant: Instant,
+    tick_duration: Duration,
 }
 
 impl DrawState {
-    pub fn new(entities: Vec<(Asset, Position)>, camera_offset: [f32; 2], time: f32) -> Self {
+    pub fn new(
+        entities: Vec<(Asset, Position, Position)>,
+        camera_positions: [[f32; 2]; 2],
+        shake: [f32; 2],
+        camera_view: CameraView,
+        times: [f32; 2],
+        tick_instant: Instant,
+        tick_duration: Duration,
+    ) -> Self {
         let mut entities = entities;
-        entities.sort_by(|x, y| y.1.y.partial_cmp(&x.1.y).unwrap());
+        entities.sort_by(|x, y| y.2.y.partial_cmp(&x.2.y).unwrap());
         Self {
             entities,
-            camera_offset,
-            time,
+            camera_positions,
+            shake,
+            camera_view,
+            times,
+            tick_instant,
+            tick_duration,
         }
     }
 
+    ///
+    /// How far between the previous and the latest tick the given moment is, from 0 to 1.
+    /// The window is drawn one tick behind the logic, so there is always a tick to interpolate towards.
+    ///
+    fn interpolation_factor(&self, now: Instant) -> f32 {
+        let since_tick = now.saturating_duration_since(self.tick_instant);
+        (since_tick.as_secs_f32() / self.tick_duration.as_secs_f32()).min(1.0)
+    }
+
+    ///
+    /// Builds what to draw at the resolution, which shows as much of the world as fits at the zoom,
+    /// centered on the camera.
+    ///
     pub fn render(
         &self,
         device: &Device,
-        uniforms: &DefaultUniforms,
         models: &HashMap<String, Model>,
-        screen_size: PhysicalSize<u32>,
+        resolution: PhysicalSize<u32>,
     ) -> Vec<DrawPackage> {
+        let factor = self.interpolation_factor(Instant::now());
+        let interpolate = |previous: f32, current: f32| previous + (current - previous) * factor;
+
+        let entities: Vec<(&Asset, Position)> = self
+            .entities
+            .iter()
+            .map(|(asset, previous, current)| {
+                (
+                    asset,
+                    Position {
+                        x: interpolate(previous.x, current.x).floor(),
+                        y: interpolate(previous.y, current.y).floor(),
+                    },
+                )
+            })
+            .collect();
+        // Clamping after interpolating keeps the view in bounds at any zoom,
+        // and shaking after clamping lets the camera shake at the edges too
+        let zoom = self.camera_view.zoom;
+        let view_size = glm::vec2(resolution.width as f32, resolution.height as f32) / zoom;
+        let mut camera_position = glm::vec2(
+            interpolate(self.camera_positions[0][0], self.camera_positions[1][0]),
+            interpolate(self.camera_positions[0][1], self.camera_positions[1][1]),
+        );
+        if let Some(bounds) = self.camera_view.bounds {
+            camera_position = bounds.clamp(camera_position, view_size);
+        }
+        // Moving the view by whole pixels keeps the pixel art from shimmering as the camera glides
+        let snap = |offset: f32| (offset * zoom).round() / zoom;
+        let uniforms = DefaultUniforms::new(
+            zoom / resolution.width as f32,
+            zoom / resolution.height as f32,
+            [
+                snap(self.shake[0] - camera_position.x + view_size.x / 2.0),
+                snap(self.shake[1] - camera_position.y + view_size.y / 2.0),
+            ],
+        );
+        let time = interpolate(self.times[0], self.times[1]);
+
         let background_vertex_array = {
-            let vertices: Vec<Vertex> = self
-                .entities
+            let vertices: Vec<Vertex> = entities
                 .iter()
                 .filter(|(asset, _)| models[&asset.name].texture() == "background")
                 .flat_map(|(asset, pos)| {
                     let model = &models[&asset.name];
 
-                    model.vertices(
-                        *pos,
-                        asset.animation,
-                        self.time - asset.animation_start_time,
-                    )
+                    model.vertices(*pos, asset.animation, time - asset.animation_start_time)
                 })
                 .collect();
-            let indices: Vec<u16> = self
-                .entities
+            let indices: Vec<u16> = entities
                 .iter()
                 .enumerate()
                 .flat_map(|(i, _)| {
@@ -78,23 +151,17 @@ impl DrawState {
         };
 
         let atlas_vertex_array = {
-            let vertices: Vec<Vertex> = self
-                .entities
+            let vertices: Vec<Vertex> = entities
                 .iter()
                 .filter(|(asset, _)| models[&asset.name].texture() == "atlas")
                 .flat_map(|(asset, pos)| {
                     let model = &models[&asset.name];
 
-                    model.vertices(
-                        *pos,
-                        asset.animation,
-                        self.time - asset.animation_start_time,
-                    )
+                    model.vertices(*pos, asset.animation, time - asset.animation_start_time)
                 })
                 .collect();
 
-            let indices: Vec<u16> = self
-                .entities
+            let indices: Vec<u16> = entities
                 .iter()
                 .enumerate()
                 .flat_map(|(i, _)| {
@@ -111,8 +178,7 @@ impl DrawState {
             VertexArray::new(device, &vertices, &indices)
         };
 
-        if self
-            .entities
+        if entities
             .iter()
             .filter(|(asset, _)| {
                 models[&asset.name].texture() != "atlas"
@@ -127,24 +193,12 @@ impl DrawState {
         vec![
             DrawPackage {
                 vertex_array: background_vertex_array,
-                uniforms: DefaultUniforms {
-                    camera_offset: [
-                        self.camera_offset[0] + (screen_size.width as f32) / 2.0,
-                        self.camera_offset[1] + (screen_size.height as f32) / 2.0,
-                    ],
-                    ..*uniforms
-                },
+                uniforms,
                 texture: TextureIdentifier::new("background".into()),
             },
             DrawPackage {
                 vertex_array: atlas_vertex_array,
-                uniforms: DefaultUniforms {
-                    camera_offset: [
-                        self.camera_offset[0] + (screen_size.width as f32) / 2.0,
-                        self.camera_offset[1] + (screen_size.height as f32) / 2.0,
-                    ],
-                    ..*uniforms
-                },
+                uniforms,
                 texture: TextureIdentifier::new("atlas".into()),
             },
         ]
@@ -1,9 +1,13 @@
+use serde::Deserialize;
 use winit::event::*;
 
 ///
 /// An event in the window that should be sent to an external event handler.
+/// Can also be read from input scripts, to drive the game without a window.
+#[derive(Debug, Deserialize)]
 pub enum ExternalEvent {
     MouseMotion {
+        #[allow(dead_code)]
         delta: (f64, f64),
     },
     KeyboardInput {
@@ -22,11 +26,8 @@ impl ExternalEvent {
             // TODO: Should different devices be handled differently?
             Event::DeviceEvent {
                 device_id: _,
-                event,
-            } => match event {
-                DeviceEvent::MouseMotion { delta } => Some(ExternalEvent::MouseMotion { delta }),
-                _ => None,
-            },
+                event: DeviceEvent::MouseMotion { delta },
+            } => Some(ExternalEvent::MouseMotion { delta }),
             Event::WindowEvent {
                 window_id: _,
                 event,
@@ -44,7 +45,7 @@ impl ExternalEvent {
                         input
                             .virtual_keycode
                             .map(|key_code| ExternalEvent::KeyboardInput {
-                                key_code: key_code,
+                                key_code,
                                 state: input.state,
                             })
                     }
@@ -40,12 +40,12 @@ impl Pipeline {
             label: Some("Render Pipeline"),
             layout: Some(&render_pipeline_layout),
             vertex: wgpu::VertexState {
-                module: &shader,
+                module: shader,
                 entry_point: "vs_main",
                 buffers: &[Vertex::desc()],
             },
             fragment: Some(wgpu::FragmentState {
-                module: &shader,
+                module: shader,
                 entry_point: "fs_main",
                 targets: &[wgpu::ColorTargetState {
                     format: config.format,
@@ -90,6 +90,17 @@ impl Pipeline {
         render_pass.set_bind_group(1, self.uniform_bind_group.as_ref().unwrap(), &[]);
     }
 
+    ///
+    /// Binds only the texture, for pipelines without uniforms.
+    ///
+    pub fn bind_texture<'a: 'b, 'b>(
+        &'a self,
+        render_pass: &mut RenderPass<'b>,
+        texture: &TextureIdentifier,
+    ) {
+        render_pass.set_bind_group(0, &self.texture_bind_groups[texture], &[]);
+    }
+
     pub fn set<'a: 'b, 'b>(&'a self, render_pass: &mut RenderPass<'b>) {
         render_pass.set_pipeline(&self.render_pipeline);
     }
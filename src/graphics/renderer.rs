@@ -10,9 +10,40 @@ use super::{
     pipeline::Pipeline,
     texture::{Texture, TextureIdentifier},
     uniforms::{DefaultUniforms, Uniform},
-    DrawState,
+    vertex_array::VertexArray,
+    DrawState, Vertex,
 };
 
+///
+/// The resolution the game is drawn at, before it is scaled up to the window.
+/// Every window shows the same part of the world, however large it is.
+///
+const RESOLUTION: PhysicalSize<u32> = PhysicalSize {
+    width: 1024,
+    height: 768,
+};
+
+///
+/// The largest area of a window of the size that shows the game at a whole multiple of its resolution, centered,
+/// as its corner and size. Windows smaller than the resolution show the game shrunk to fit instead.
+///
+fn letterbox(window_size: PhysicalSize<u32>) -> (f32, f32, f32, f32) {
+    let fit = (window_size.width as f32 / RESOLUTION.width as f32)
+        .min(window_size.height as f32 / RESOLUTION.height as f32);
+    let scale = if fit >= 1.0 { fit.floor() } else { fit };
+    let width = RESOLUTION.width as f32 * scale;
+    let height = RESOLUTION.height as f32 * scale;
+    (
+        ((window_size.width as f32 - width) / 2.0).floor(),
+        ((window_size.height as f32 - height) / 2.0).floor(),
+        width,
+        height,
+    )
+}
+
+/// The texture the game is drawn to at its resolution, as the upscale pipeline knows it.
+const SCENE_TEXTURE: &str = "scene";
+
 ///
 /// Contains everything needed to interact with the WGPU rendering system
 ///
@@ -26,8 +57,15 @@ pub struct Renderer {
     pipeline: Pipeline,
     default_uniforms: Uniform<DefaultUniforms>,
 
+    /// The game drawn at its resolution, which is then scaled up to the window.
+    scene: Texture,
+    upscale_pipeline: Pipeline,
+    /// A rectangle over the whole viewport, for drawing the scene on.
+    screen_quad: VertexArray,
+
     models: HashMap<String, Model>,
-    textures: HashMap<TextureIdentifier, Texture>,
+    /// Kept alive for as long as the pipeline's texture bind groups refer to them.
+    _textures: HashMap<TextureIdentifier, Texture>,
 }
 
 impl Renderer {
@@ -78,8 +116,8 @@ impl Renderer {
         let default_uniforms = Uniform::new(
             &device,
             DefaultUniforms::new(
-                1.0 / (size.width as f32),
-                1.0 / (size.height as f32),
+                1.0 / (RESOLUTION.width as f32),
+                1.0 / (RESOLUTION.height as f32),
                 [0.0, 0.0],
             ),
         );
@@ -104,6 +142,46 @@ impl Renderer {
         }
         pipeline.set_uniform_bind_group(&device, &default_uniforms);
 
+        let scene =
+            Texture::new_render_target(&device, RESOLUTION.width, RESOLUTION.height, config.format);
+        let upscale_shader = device.create_shader_module(&wgpu::ShaderModuleDescriptor {
+            label: Some("Upscale Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shaders/upscale.wgsl").into()),
+        });
+        let mut upscale_pipeline = Pipeline::new(
+            &device,
+            &upscale_shader,
+            &config,
+            vec![Texture::create_bind_group_layout(&device)],
+        );
+        upscale_pipeline.create_texture_bind_group(
+            &device,
+            &scene,
+            &TextureIdentifier::new(SCENE_TEXTURE.into()),
+        );
+        let screen_quad = VertexArray::new(
+            &device,
+            &[
+                Vertex {
+                    position: [-1.0, -1.0, 0.0],
+                    tex_coords: [0.0, 1.0],
+                },
+                Vertex {
+                    position: [1.0, -1.0, 0.0],
+                    tex_coords: [1.0, 1.0],
+                },
+                Vertex {
+                    position: [1.0, 1.0, 0.0],
+                    tex_coords: [1.0, 0.0],
+                },
+                Vertex {
+                    position: [-1.0, 1.0, 0.0],
+                    tex_coords: [0.0, 0.0],
+                },
+            ],
+            &[0, 1, 2, 0, 2, 3],
+        );
+
         let models = Self::load_models();
 
         println!("Returning renderer");
@@ -117,7 +195,11 @@ impl Renderer {
 
             default_uniforms,
 
-            textures,
+            scene,
+            upscale_pipeline,
+            screen_quad,
+
+            _textures: textures,
             models,
         }
     }
@@ -137,7 +219,11 @@ impl Renderer {
             "atlas".into(),
             16,
             96.0,
-            vec![Animation::new(vec![2], 1.0, false)],
+            vec![
+                Animation::new(vec![2], 1.0, false),
+                // Dying: flickers, then stays on an empty cell of the atlas
+                Animation::new(vec![2, 255, 2, 255, 2, 255], 0.1, true),
+            ],
         );
         models.insert("bush".into(), bush_model);
 
@@ -181,15 +267,27 @@ impl Renderer {
         );
         models.insert("firefly".into(), firefly_model);
 
+        let wisp_model = Model::new(
+            "atlas".into(),
+            32,
+            96.0,
+            vec![
+                Animation::new(vec![6, 7, 38, 39, 38, 7], 0.1, false),
+                // Dying: fades out, then stays on an empty cell of the atlas
+                Animation::new(vec![7, 38, 39, 1023], 0.1, true),
+            ],
+        );
+        models.insert("wisp".into(), wisp_model);
+
         models
     }
 
     fn load_textures(device: &Device, queue: &Queue) -> HashMap<TextureIdentifier, Texture> {
         let atlas_bytes = include_bytes!("atlas.png");
-        let atlas_texture = Texture::new(&device, &queue, atlas_bytes);
+        let atlas_texture = Texture::new(device, queue, atlas_bytes);
 
         let background_bytes = include_bytes!("background.png");
-        let background_texture = Texture::new(&device, &queue, background_bytes);
+        let background_texture = Texture::new(device, queue, background_bytes);
 
         let mut textures = HashMap::new();
         textures.insert(TextureIdentifier::new("atlas".into()), atlas_texture);
@@ -210,20 +308,14 @@ impl Renderer {
             self.config.width = new_size.width;
             self.config.height = new_size.height;
             self.surface.configure(&self.device, &self.config);
-            self.default_uniforms.update_uniform(
-                |x| {
-                    x.x_scale = 1.0 / (new_size.width as f32);
-                    x.y_scale = 1.0 / (new_size.height as f32)
-                },
-                &self.queue,
-            )
         }
     }
 
     ///
-    /// Renders the given DrawState using the default pipeline.
+    /// Renders the given DrawState using the default pipeline at the game's resolution,
+    /// then scales it up to the window with bars around it where the window's shape differs.
     ///
-    pub fn render(&mut self, draw_state: DrawState) -> Result<(), wgpu::SurfaceError> {
+    pub fn render(&mut self, draw_state: &DrawState) -> Result<(), wgpu::SurfaceError> {
         let output = self.surface.get_current_texture().unwrap();
 
         let view = output
@@ -236,17 +328,12 @@ impl Renderer {
                 label: Some("Render Encoder"),
             });
 
-        let mut draw_packages = draw_state.render(
-            &self.device,
-            self.default_uniforms.uniform_struct(),
-            &self.models,
-            self.size,
-        );
+        let mut draw_packages = draw_state.render(&self.device, &self.models, RESOLUTION);
 
         let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
             label: Some("Render Pass"),
             color_attachments: &[wgpu::RenderPassColorAttachment {
-                view: &view,
+                view: self.scene.view(),
                 resolve_target: None,
                 ops: wgpu::Operations {
                     load: wgpu::LoadOp::Clear(wgpu::Color {
@@ -273,8 +360,59 @@ impl Renderer {
         }
         drop(render_pass);
 
+        let mut upscale_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Upscale Pass"),
+            color_attachments: &[wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: true,
+                },
+            }],
+            depth_stencil_attachment: None,
+        });
+        let (x, y, width, height) = letterbox(self.size);
+        upscale_pass.set_viewport(x, y, width, height, 0.0, 1.0);
+        self.upscale_pipeline.set(&mut upscale_pass);
+        self.upscale_pipeline.bind_texture(
+            &mut upscale_pass,
+            &TextureIdentifier::new(SCENE_TEXTURE.into()),
+        );
+        self.screen_quad.draw(&mut upscale_pass);
+        drop(upscale_pass);
+
         self.queue.submit(std::iter::once(encoder.finish()));
         output.present();
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn letterbox_of(width: u32, height: u32) -> (f32, f32, f32, f32) {
+        letterbox(PhysicalSize { width, height })
+    }
+
+    #[test]
+    fn exact_multiples_of_the_resolution_fill_the_window() {
+        assert_eq!(letterbox_of(1024, 768), (0.0, 0.0, 1024.0, 768.0));
+        assert_eq!(letterbox_of(3072, 2304), (0.0, 0.0, 3072.0, 2304.0));
+    }
+
+    #[test]
+    fn other_windows_get_bars_around_a_whole_multiple() {
+        // Wide windows get bars on the sides, and the scale stays whole even with room to spare
+        assert_eq!(letterbox_of(2560, 1600), (256.0, 32.0, 2048.0, 1536.0));
+        // Tall windows get bars above and below, rounded down to whole pixels
+        assert_eq!(letterbox_of(1025, 1601), (0.0, 416.0, 1024.0, 768.0));
+    }
+
+    #[test]
+    fn small_windows_show_the_game_shrunk_to_fit() {
+        assert_eq!(letterbox_of(512, 768), (0.0, 192.0, 512.0, 384.0));
+        assert_eq!(letterbox_of(800, 300), (200.0, 0.0, 400.0, 300.0));
+    }
+}
@@ -0,0 +1,36 @@
+// Draws the game, rendered at its own resolution, stretched over the whole viewport.
+// The viewport is set to a whole multiple of that resolution, so every pixel of the game becomes a square of pixels.
+
+// Vertex shader
+
+struct VertexInput {
+    [[location(0)]] position : vec3<f32>;
+    [[location(1)]] tex_coords : vec2<f32>;
+};
+
+struct VertexOutput {
+    [[builtin(position)]] clip_position : vec4<f32>;
+    [[location(0)]] tex_coords : vec2<f32>;
+};
+
+[[stage(vertex)]]
+fn vs_main(
+   model : VertexInput
+) -> VertexOutput {
+    var out : VertexOutput;
+    out.tex_coords = model.tex_coords;
+    out.clip_position = vec4<f32>(model.position, 1.0);
+    return out;
+}
+
+// Fragment shader
+
+[[group(0), binding(0)]]
+var t_scene : texture_2d<f32>;
+[[group(0), binding(1)]]
+var s_scene : sampler;
+
+[[stage(fragment)]]
+fn fs_main(in : VertexOutput) -> [[location(0)]] vec4<f32> {
+    return textureSample(t_scene, s_scene, in.tex_coords);
+}
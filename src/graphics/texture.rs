@@ -56,6 +56,37 @@ impl Texture {
             texture_size,
         );
 
+        Self::from_wgpu_texture(device, &wgpu_texture)
+    }
+
+    ///
+    /// Creates an empty texture of the size that can be rendered to, and then sampled like any other texture.
+    ///
+    pub fn new_render_target(
+        device: &Device,
+        width: u32,
+        height: u32,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let wgpu_texture = device.create_texture(&wgpu::TextureDescriptor {
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT,
+            label: Some("render target"),
+        });
+
+        Self::from_wgpu_texture(device, &wgpu_texture)
+    }
+
+    /// Samples the nearest texel, so pixel art stays crisp when it is scaled up.
+    fn from_wgpu_texture(device: &Device, wgpu_texture: &wgpu::Texture) -> Self {
         let texture_view = wgpu_texture.create_view(&wgpu::TextureViewDescriptor::default());
         let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
             address_mode_u: wgpu::AddressMode::ClampToEdge,
@@ -73,13 +104,17 @@ impl Texture {
         }
     }
 
+    pub fn view(&self) -> &TextureView {
+        &self.view
+    }
+
     pub fn create_bind_group(
         &self,
         device: &Device,
         bind_group_layout: &BindGroupLayout,
     ) -> BindGroup {
         let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
-            layout: &bind_group_layout,
+            layout: bind_group_layout,
             entries: &[
                 wgpu::BindGroupEntry {
                     binding: 0,
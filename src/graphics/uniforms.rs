@@ -60,10 +60,6 @@ impl<T: Pod + Zeroable> Uniform<T> {
             bytemuck::cast_slice(&[self.uniform_struct]),
         )
     }
-
-    pub fn uniform_struct(&self) -> &T {
-        &self.uniform_struct
-    }
 }
 
 #[repr(C)]
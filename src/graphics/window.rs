@@ -1,5 +1,5 @@
 use super::renderer::Renderer;
-use super::ExternalEvent;
+use super::{DrawState, ExternalEvent};
 use crate::channels::{LogicToWindowReceiver, WindowToLogicSender};
 use winit::dpi::PhysicalSize;
 use winit::event::{Event, WindowEvent};
@@ -22,6 +22,8 @@ pub struct Window {
     window: winit::window::Window,
     rx: LogicToWindowReceiver,
     renderer: Renderer,
+    /// The latest state received from the logic thread, drawn again every frame until a new one arrives.
+    draw_state: Option<DrawState>,
 }
 
 pub type EventHandler = Box<dyn FnMut(winit::event::Event<()>) + Send + 'static>;
@@ -48,14 +50,13 @@ impl Window {
         //graphics::RenderCaller::initialize_gl(&windowed_context);
         // let render_caller = RenderCaller::new(screen_dimensions);
 
-        let res = Window {
+        Window {
             event_loop: Some(el),
             window,
             rx,
             renderer,
-        };
-
-        res
+            draw_state: None,
+        }
     }
 
     // fn get_capabilities(&self) -> GraphicsCapabilities {
@@ -92,13 +93,17 @@ impl Window {
 
         match self.rx.render_pack.try_recv() {
             Ok(draw_state) => {
-                self.renderer.render(draw_state).unwrap();
+                self.draw_state = Some(draw_state);
             }
             Err(std::sync::mpsc::TryRecvError::Disconnected) => {
                 panic!("The logic thread has disconnected!");
             }
             _ => {}
         }
+
+        if let Some(draw_state) = &self.draw_state {
+            self.renderer.render(draw_state).unwrap();
+        }
     }
 
     fn update_screen_dimensions(&mut self, screen_dimensions: PhysicalSize<u32>) {
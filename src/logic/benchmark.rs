@@ -0,0 +1,196 @@
+//!
+//! Benchmarks of the collision queries against the simple scans they replace.
+//! They are ignored tests, run with `cargo test --release -- --ignored --nocapture benchmark`.
+//!
+
+use std::time::{Duration, Instant};
+
+use rand::{prelude::StdRng, Rng, SeedableRng};
+
+use super::{
+    collision::{
+        Aabb, CollisionMesh, CollisionMeshManager, Shape, SpatialHash, Triangle, WorldCollisionMesh,
+    },
+    game::BROADPHASE_CELL_SIZE,
+};
+
+/// How many times each benchmark repeats its work, so single runs don't skew the timings.
+const RUNS: u32 = 10;
+
+fn time_runs<T>(mut run: impl FnMut() -> T) -> (T, Duration) {
+    let start_time = Instant::now();
+    let mut result = run();
+    for _ in 1..RUNS {
+        result = run();
+    }
+    (result, start_time.elapsed() / RUNS)
+}
+
+///
+/// Times finding the colliding pairs among the given number of colliders,
+/// scattered as densely as the colliders of a busy level,
+/// by testing every pair and with the broadphase.
+///
+fn run_broadphase_benchmark(colliders: usize) {
+    let mut collision_mesh_manager = CollisionMeshManager::new();
+    let basic = collision_mesh_manager.add_collision_mesh(CollisionMesh::new(Shape::Aabb(Aabb {
+        min_x: -0.5,
+        min_y: -0.5,
+        max_x: 0.5,
+        max_y: 0.5,
+    })));
+
+    // About one collider per broadphase cell
+    let extent = (colliders as f32).sqrt() * BROADPHASE_CELL_SIZE / 2.0;
+    let mut rng = StdRng::seed_from_u64(0);
+    let meshes: Vec<CollisionMesh> = (0..colliders)
+        .map(|_| {
+            collision_mesh_manager.get_collision_mesh(
+                basic,
+                glm::vec2(
+                    rng.gen_range(-extent..extent),
+                    rng.gen_range(-extent..extent),
+                ),
+                rng.gen_range(16.0..96.0),
+            )
+        })
+        .collect();
+    let aabbs: Vec<Aabb> = meshes.iter().map(|mesh| mesh.aabb).collect();
+
+    let (brute_force_pairs, brute_force_time) = time_runs(|| {
+        let mut pairs = 0;
+        for (i, mesh_1) in meshes.iter().enumerate() {
+            for mesh_2 in &meshes[i + 1..] {
+                if mesh_1.is_colliding(mesh_2) {
+                    pairs += 1;
+                }
+            }
+        }
+        pairs
+    });
+
+    let mut broadphase = SpatialHash::new(BROADPHASE_CELL_SIZE);
+    let (broadphase_pairs, broadphase_time) =
+        time_runs(|| broadphase.colliding_pairs(&aabbs).len());
+
+    println!(
+        "{} colliders, {} colliding pairs",
+        colliders, brute_force_pairs
+    );
+    println!("  every pair: {:?}", brute_force_time);
+    println!("  broadphase: {:?}", broadphase_time);
+    assert_eq!(
+        broadphase_pairs, brute_force_pairs,
+        "the broadphase found different colliding pairs"
+    );
+}
+
+/// How many colliders are tested against the world collision mesh, about as many as in a busy level.
+const WORLD_COLLISION_QUERIES: usize = 1000;
+
+///
+/// Scans every triangle for the collision, the way `WorldCollisionMesh::find_collision` did before it had a BVH.
+/// It must give the same results.
+///
+fn find_collision_by_scanning(triangles: &[Triangle], aabb: &Aabb) -> Option<glm::Vec2> {
+    let mut intersection_vector = None;
+    let mut intersection_dist = 0.0;
+    for triangle in triangles.iter() {
+        if triangle.is_colliding(aabb) {
+            let test_intersection_vector = triangle.closest_intersection_vector(aabb);
+            if test_intersection_vector.magnitude_squared() > intersection_dist {
+                intersection_dist = test_intersection_vector.magnitude_squared();
+                intersection_vector = Some(test_intersection_vector);
+            }
+        }
+    }
+    intersection_vector
+}
+
+///
+/// Times finding the collisions of colliders with a world collision mesh of the given number of triangles,
+/// by scanning every triangle and with the BVH.
+///
+fn run_world_collision_benchmark(triangles: usize) {
+    let extent = (triangles as f32).sqrt() * 32.0;
+    let mut rng = StdRng::seed_from_u64(0);
+    let random_point = |rng: &mut StdRng| {
+        glm::vec2(
+            rng.gen_range(-extent..extent),
+            rng.gen_range(-extent..extent),
+        )
+    };
+
+    let world_triangles: Vec<Triangle> = (0..triangles)
+        .map(|_| {
+            let center = random_point(&mut rng);
+            // Corners at increasing angles, so the triangle is counter clockwise
+            let mut angle = rng.gen_range(0.0..std::f32::consts::TAU);
+            let mut corner = || {
+                let corner =
+                    center + glm::vec2(angle.cos(), angle.sin()) * rng.gen_range(8.0..48.0);
+                angle += std::f32::consts::TAU / 3.0;
+                corner
+            };
+            Triangle::new(corner(), corner(), corner())
+        })
+        .collect();
+    let aabbs: Vec<Aabb> = (0..WORLD_COLLISION_QUERIES)
+        .map(|_| {
+            let size = rng.gen_range(16.0..96.0);
+            let corner = random_point(&mut rng);
+            Aabb {
+                min_x: corner.x,
+                min_y: corner.y,
+                max_x: corner.x + size,
+                max_y: corner.y + size,
+            }
+        })
+        .collect();
+    let world_collision_mesh = WorldCollisionMesh::new(world_triangles.clone());
+
+    let (scanned_collisions, scanning_time) = time_runs(|| {
+        aabbs
+            .iter()
+            .map(|aabb| find_collision_by_scanning(&world_triangles, aabb))
+            .collect::<Vec<_>>()
+    });
+    let (bvh_collisions, bvh_time) = time_runs(|| {
+        aabbs
+            .iter()
+            .map(|aabb| world_collision_mesh.find_collision(aabb))
+            .collect::<Vec<_>>()
+    });
+
+    println!(
+        "{} triangles, {} colliders, {} colliding",
+        triangles,
+        WORLD_COLLISION_QUERIES,
+        scanned_collisions
+            .iter()
+            .filter(|collision| collision.is_some())
+            .count()
+    );
+    println!("  every triangle: {:?}", scanning_time);
+    println!("  bvh:            {:?}", bvh_time);
+    assert!(
+        bvh_collisions == scanned_collisions,
+        "the bvh found different collisions"
+    );
+}
+
+#[test]
+#[ignore = "benchmark"]
+fn broadphase_benchmark() {
+    for colliders in [100, 1000, 10000] {
+        run_broadphase_benchmark(colliders);
+    }
+}
+
+#[test]
+#[ignore = "benchmark"]
+fn world_collision_benchmark() {
+    for triangles in [100, 1000, 10000] {
+        run_world_collision_benchmark(triangles);
+    }
+}
@@ -0,0 +1,261 @@
+use glm::Vec2;
+use serde::Deserialize;
+
+use super::steering::truncate;
+
+/// The area the camera may show, in world coordinates.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+pub struct CameraBounds {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl CameraBounds {
+    ///
+    /// Moves the center of a view of the size so the view stays within the bounds.
+    /// Views larger than the bounds are centered on them instead.
+    ///
+    pub fn clamp(&self, center: Vec2, view_size: Vec2) -> Vec2 {
+        let clamp_axis = |center: f32, half_view: f32, min: f32, max: f32| {
+            if max - min <= half_view * 2.0 {
+                (min + max) / 2.0
+            } else {
+                center.clamp(min + half_view, max - half_view)
+            }
+        };
+        glm::vec2(
+            clamp_axis(center.x, view_size.x / 2.0, self.min.x, self.max.x),
+            clamp_axis(center.y, view_size.y / 2.0, self.min.y, self.max.y),
+        )
+    }
+}
+
+/// How the camera shows the world around its position.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraView {
+    /// The area the camera keeps its view in, if it has one.
+    pub bounds: Option<CameraBounds>,
+    /// How many pixels of the game's resolution a world unit takes up.
+    pub zoom: f32,
+}
+
+/// How the camera follows the player, as given by a level.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Size of the box around the camera's center that the player moves in freely, without the camera following.
+    pub dead_zone: Vec2,
+    /// About how long the camera takes to catch up with the player. At 0, it keeps up right away.
+    pub smoothing_seconds: f32,
+    /// How far ahead the camera looks, as the seconds it takes the player to get there at their current velocity.
+    pub look_ahead_seconds: f32,
+    /// The furthest the camera looks ahead, in pixels.
+    pub max_look_ahead: f32,
+    /// Without bounds, the camera goes wherever the player goes.
+    pub bounds: Option<CameraBounds>,
+    /// How many pixels of the game's resolution a world unit takes up. Above 1, the camera zooms in. Must be above 0.
+    pub zoom: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            dead_zone: glm::vec2(96.0, 64.0),
+            smoothing_seconds: 0.2,
+            look_ahead_seconds: 0.25,
+            max_look_ahead: 160.0,
+            bounds: None,
+            zoom: 1.0,
+        }
+    }
+}
+
+impl CameraSettings {
+    ///
+    /// Returns the name and value of a setting the camera can't work with, if there is one.
+    /// None of the settings can be negative, and the zoom must be above 0.
+    ///
+    pub fn invalid_setting(&self) -> Option<(&'static str, f32)> {
+        if self.zoom.is_nan() || self.zoom <= 0.0 {
+            return Some(("zoom", self.zoom));
+        }
+        [
+            ("dead_zone", self.dead_zone.x),
+            ("dead_zone", self.dead_zone.y),
+            ("smoothing_seconds", self.smoothing_seconds),
+            ("look_ahead_seconds", self.look_ahead_seconds),
+            ("max_look_ahead", self.max_look_ahead),
+        ]
+        .into_iter()
+        .find(|(_, value)| value.is_nan() || *value < 0.0)
+    }
+}
+
+///
+/// Where the view of the world is centered, following the player smoothly.
+/// The camera is only looked at when drawing, so it has no effect on the game itself.
+///
+pub struct Camera {
+    settings: CameraSettings,
+    position: Vec2,
+}
+
+impl Camera {
+    /// Starts centered on the target.
+    pub fn new(settings: CameraSettings, target: Vec2) -> Self {
+        Self {
+            settings,
+            position: target,
+        }
+    }
+
+    pub fn position(&self) -> Vec2 {
+        self.position
+    }
+
+    pub fn view(&self) -> CameraView {
+        CameraView {
+            bounds: self.settings.bounds,
+            zoom: self.settings.zoom,
+        }
+    }
+
+    /// Centers the camera on the target right away, like after loading a game.
+    pub fn snap_to(&mut self, target: Vec2) {
+        self.position = target;
+    }
+
+    ///
+    /// Moves the camera towards the target, looking ahead in the direction it moves.
+    /// The camera only follows once the point it looks at leaves the dead zone, and then only as far as its edge.
+    ///
+    pub fn update(&mut self, target: Vec2, target_velocity: Vec2, elapsed_seconds: f32) {
+        let look_ahead = target_velocity * self.settings.look_ahead_seconds;
+        let focus = target + truncate(look_ahead, self.settings.max_look_ahead);
+
+        let offset = focus - self.position;
+        let outside_dead_zone = |offset: f32, dead_zone: f32| {
+            let half = dead_zone / 2.0;
+            offset - offset.clamp(-half, half)
+        };
+        let goal = self.position
+            + glm::vec2(
+                outside_dead_zone(offset.x, self.settings.dead_zone.x),
+                outside_dead_zone(offset.y, self.settings.dead_zone.y),
+            );
+
+        // Closes the same share of the distance every second, whatever the tick rate
+        let catch_up = if self.settings.smoothing_seconds > 0.0 {
+            1.0 - (-elapsed_seconds / self.settings.smoothing_seconds).exp()
+        } else {
+            1.0
+        };
+        self.position += (goal - self.position) * catch_up;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A camera at the origin.
+    fn camera(settings: CameraSettings) -> Camera {
+        Camera::new(settings, Vec2::zeros())
+    }
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).norm() < 0.001,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    /// Settings that keep the camera on the player, without a dead zone, smoothing or looking ahead.
+    fn still() -> CameraSettings {
+        CameraSettings {
+            dead_zone: Vec2::zeros(),
+            smoothing_seconds: 0.0,
+            look_ahead_seconds: 0.0,
+            max_look_ahead: 0.0,
+            bounds: None,
+            zoom: 1.0,
+        }
+    }
+
+    #[test]
+    fn the_camera_stays_put_while_the_player_is_in_the_dead_zone() {
+        let mut camera = camera(CameraSettings {
+            dead_zone: glm::vec2(100.0, 60.0),
+            ..still()
+        });
+        camera.update(glm::vec2(40.0, -25.0), Vec2::zeros(), 0.1);
+        assert_near(camera.position(), Vec2::zeros());
+        camera.update(glm::vec2(-50.0, 30.0), Vec2::zeros(), 0.1);
+        assert_near(camera.position(), Vec2::zeros());
+
+        // Leaving it, the camera follows only as far as the edge
+        camera.update(glm::vec2(80.0, 0.0), Vec2::zeros(), 0.1);
+        assert_near(camera.position(), glm::vec2(30.0, 0.0));
+    }
+
+    #[test]
+    fn smoothing_catches_up_the_same_way_at_any_tick_rate() {
+        let smooth = CameraSettings {
+            smoothing_seconds: 0.2,
+            ..still()
+        };
+        let target = glm::vec2(100.0, 0.0);
+        let mut fast_ticks = camera(smooth);
+        let mut distances = Vec::new();
+        for _ in 0..120 {
+            fast_ticks.update(target, Vec2::zeros(), 1.0 / 60.0);
+            distances.push((target - fast_ticks.position()).norm());
+        }
+        // It never overshoots, and after 10 smoothing periods it is all but there
+        assert!(distances.windows(2).all(|pair| pair[1] < pair[0]));
+        assert!(distances[119] < 0.01);
+
+        let mut slow_ticks = camera(smooth);
+        for _ in 0..2 {
+            slow_ticks.update(target, Vec2::zeros(), 1.0);
+        }
+        assert_near(slow_ticks.position(), fast_ticks.position());
+    }
+
+    #[test]
+    fn looking_ahead_goes_no_further_than_the_max() {
+        let look_ahead = CameraSettings {
+            look_ahead_seconds: 0.25,
+            max_look_ahead: 160.0,
+            ..still()
+        };
+        let mut camera = camera(look_ahead);
+        camera.update(Vec2::zeros(), glm::vec2(200.0, 0.0), 0.1);
+        assert_near(camera.position(), glm::vec2(50.0, 0.0));
+        camera.update(Vec2::zeros(), glm::vec2(0.0, -1000.0), 0.1);
+        assert_near(camera.position(), glm::vec2(0.0, -160.0));
+    }
+
+    #[test]
+    fn views_stay_in_the_bounds_or_are_centered_on_them() {
+        let bounds = CameraBounds {
+            min: glm::vec2(0.0, 0.0),
+            max: glm::vec2(100.0, 50.0),
+        };
+        assert_near(
+            bounds.clamp(glm::vec2(50.0, 25.0), glm::vec2(40.0, 20.0)),
+            glm::vec2(50.0, 25.0),
+        );
+        assert_near(
+            bounds.clamp(glm::vec2(-30.0, 80.0), glm::vec2(40.0, 20.0)),
+            glm::vec2(20.0, 40.0),
+        );
+        // Too wide for the bounds, but not too high
+        assert_near(
+            bounds.clamp(glm::vec2(-30.0, 0.0), glm::vec2(200.0, 20.0)),
+            glm::vec2(50.0, 10.0),
+        );
+    }
+}
@@ -1,7 +1,9 @@
 use std::ops::{Add, Mul};
 
+use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
+use super::{sweep, Ray, RayHit};
 use crate::logic::Direction;
 
 #[derive(Copy, Clone, Debug)]
@@ -37,6 +39,76 @@ impl Aabb {
         }
     }
 
+    pub fn corners(&self) -> Vec<glm::Vec2> {
+        AabbCorner::iter()
+            .map(|corner| self.get_corner(corner))
+            .collect()
+    }
+
+    ///
+    /// Returns when this AABB, moving by the displacement, first touches the other AABB,
+    /// as a fraction of the displacement.
+    ///
+    pub fn time_of_impact(&self, displacement: glm::Vec2, aabb: &Aabb) -> Option<f32> {
+        sweep::time_of_impact(
+            &self.corners(),
+            displacement,
+            &aabb.corners(),
+            &[glm::vec2(1.0, 0.0), glm::vec2(0.0, 1.0)],
+        )
+    }
+
+    ///
+    /// Returns where the ray first enters this AABB.
+    /// Rays starting inside the AABB don't hit it.
+    ///
+    pub fn cast_ray(&self, ray: &Ray) -> Option<RayHit> {
+        let start = ray.start_point();
+        let vector = ray.vector();
+        let mut enter_time = f32::NEG_INFINITY;
+        let mut exit_time = f32::INFINITY;
+        let mut normal = glm::vec2(0.0, 0.0);
+        for (start, vector, min, max, axis) in [
+            (
+                start.x,
+                vector.x,
+                self.min_x,
+                self.max_x,
+                glm::vec2(1.0, 0.0),
+            ),
+            (
+                start.y,
+                vector.y,
+                self.min_y,
+                self.max_y,
+                glm::vec2(0.0, 1.0),
+            ),
+        ] {
+            if vector.abs() < 0.00001 {
+                if start <= min || start >= max {
+                    return None;
+                }
+                continue;
+            }
+            let (near, far, near_normal) = if vector > 0.0 {
+                ((min - start) / vector, (max - start) / vector, -axis)
+            } else {
+                ((max - start) / vector, (min - start) / vector, axis)
+            };
+            if near > enter_time {
+                enter_time = near;
+                normal = near_normal;
+            }
+            exit_time = exit_time.min(far);
+        }
+
+        if enter_time <= exit_time && (0.0..=1.0).contains(&enter_time) {
+            Some(ray.hit(enter_time, normal))
+        } else {
+            None
+        }
+    }
+
     pub fn is_inside(&self, point: glm::Vec2) -> bool {
         point.x > self.min_x && point.y > self.min_y && point.x < self.max_x && point.y < self.max_y
     }
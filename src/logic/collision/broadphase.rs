@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+
+use super::Aabb;
+
+///
+/// Finds which AABBs collide without testing every AABB against every other one,
+/// by sorting them into the cells of a uniform grid first.
+/// Only AABBs that share a cell are tested against each other.
+///
+/// Works best with cells a bit larger than most of the AABBs.
+///
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+        }
+    }
+
+    fn cell(&self, x: f32, y: f32) -> (i32, i32) {
+        (
+            (x / self.cell_size).floor() as i32,
+            (y / self.cell_size).floor() as i32,
+        )
+    }
+
+    fn insert(&mut self, index: usize, aabb: &Aabb) {
+        let (min_x, min_y) = self.cell(aabb.min_x, aabb.min_y);
+        let (max_x, max_y) = self.cell(aabb.max_x, aabb.max_y);
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                self.cells.entry((x, y)).or_default().push(index);
+            }
+        }
+    }
+
+    ///
+    /// Returns the indices of every pair of colliding AABBs, with the smaller index first.
+    /// The pairs are sorted, so they come out in the same order every time.
+    ///
+    pub fn colliding_pairs(&mut self, aabbs: &[Aabb]) -> Vec<(usize, usize)> {
+        // Cells used last time are likely used again, so they keep their memory until they go unused.
+        self.cells.retain(|_, indices| {
+            let used = !indices.is_empty();
+            indices.clear();
+            used
+        });
+        for (index, aabb) in aabbs.iter().enumerate() {
+            self.insert(index, aabb);
+        }
+
+        let mut pairs = Vec::new();
+        for indices in self.cells.values() {
+            for (i, &index_1) in indices.iter().enumerate() {
+                for &index_2 in &indices[i + 1..] {
+                    if aabbs[index_1].is_colliding(&aabbs[index_2]) {
+                        pairs.push((index_1.min(index_2), index_1.max(index_2)));
+                    }
+                }
+            }
+        }
+        // AABBs spanning several cells can meet in more than one of them
+        pairs.sort_unstable();
+        pairs.dedup();
+        pairs
+    }
+}
@@ -0,0 +1,116 @@
+use super::Aabb;
+
+/// How many AABBs a leaf holds at most before it is split in two.
+const MAX_LEAF_SIZE: usize = 4;
+
+enum BvhNode {
+    Leaf {
+        aabb: Aabb,
+        /// Range of `Bvh::indices` with the AABBs in this leaf.
+        start: usize,
+        end: usize,
+    },
+    Branch {
+        aabb: Aabb,
+        left: usize,
+        right: usize,
+    },
+}
+
+impl BvhNode {
+    fn aabb(&self) -> &Aabb {
+        match self {
+            BvhNode::Leaf { aabb, .. } | BvhNode::Branch { aabb, .. } => aabb,
+        }
+    }
+}
+
+///
+/// A bounding volume hierarchy over a fixed set of AABBs,
+/// for finding which of them collide with another AABB without testing every one.
+///
+/// Each node surrounds all the AABBs below it, and is split in half along its longest axis.
+///
+pub struct Bvh {
+    aabbs: Vec<Aabb>,
+    nodes: Vec<BvhNode>,
+    /// Indices of the AABBs the hierarchy was built from, in the order the leaves refer to them.
+    indices: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn new(aabbs: &[Aabb]) -> Self {
+        let mut bvh = Self {
+            aabbs: aabbs.to_vec(),
+            nodes: Vec::new(),
+            indices: (0..aabbs.len()).collect(),
+        };
+        if !aabbs.is_empty() {
+            bvh.build(aabbs, 0, aabbs.len());
+        }
+        bvh
+    }
+
+    /// Builds the node for `indices[start..end]` and everything below it, returning its index.
+    fn build(&mut self, aabbs: &[Aabb], start: usize, end: usize) -> usize {
+        let node_aabbs: Vec<Aabb> = self.indices[start..end]
+            .iter()
+            .map(|index| aabbs[*index])
+            .collect();
+        let aabb = Aabb::surrounding_aabb(&node_aabbs);
+
+        if end - start <= MAX_LEAF_SIZE {
+            self.nodes.push(BvhNode::Leaf { aabb, start, end });
+            return self.nodes.len() - 1;
+        }
+
+        let split_x = aabb.max_x - aabb.min_x > aabb.max_y - aabb.min_y;
+        self.indices[start..end].sort_by(|index_1, index_2| {
+            let (aabb_1, aabb_2) = (&aabbs[*index_1], &aabbs[*index_2]);
+            let (center_1, center_2) = if split_x {
+                (aabb_1.min_x + aabb_1.max_x, aabb_2.min_x + aabb_2.max_x)
+            } else {
+                (aabb_1.min_y + aabb_1.max_y, aabb_2.min_y + aabb_2.max_y)
+            };
+            center_1.total_cmp(&center_2)
+        });
+
+        // The branch is pushed before its children, so it is filled in once they are built.
+        let node = self.nodes.len();
+        self.nodes.push(BvhNode::Leaf { aabb, start, end });
+        let middle = (start + end) / 2;
+        let left = self.build(aabbs, start, middle);
+        let right = self.build(aabbs, middle, end);
+        self.nodes[node] = BvhNode::Branch { aabb, left, right };
+        node
+    }
+
+    /// Returns the indices of the AABBs colliding with the given AABB, in ascending order.
+    pub fn find_colliding(&self, aabb: &Aabb) -> Vec<usize> {
+        let mut colliding = Vec::new();
+        if self.nodes.is_empty() {
+            return colliding;
+        }
+
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let node = &self.nodes[node];
+            if !node.aabb().is_colliding(aabb) {
+                continue;
+            }
+            match node {
+                BvhNode::Leaf { start, end, .. } => colliding.extend(
+                    self.indices[*start..*end]
+                        .iter()
+                        .filter(|index| self.aabbs[**index].is_colliding(aabb)),
+                ),
+                BvhNode::Branch { left, right, .. } => {
+                    stack.push(*left);
+                    stack.push(*right);
+                }
+            }
+        }
+        colliding.sort_unstable();
+        colliding
+    }
+}
@@ -1,27 +1,32 @@
 use nalgebra_glm::Vec2;
 
-use super::Aabb;
+use super::{Aabb, Shape};
 
 pub struct CollisionMesh {
+    /// Surrounds the shape, for where a rough shape is enough,
+    /// like the broadphase and collisions with the world collision mesh.
     pub aabb: Aabb,
+    pub shape: Shape,
 }
 
 impl CollisionMesh {
-    pub fn new(aabb: Aabb) -> Self {
-        Self { aabb }
+    pub fn new(shape: Shape) -> Self {
+        Self {
+            aabb: shape.surrounding_aabb(),
+            shape,
+        }
     }
 
     pub fn is_colliding(&self, mesh: &CollisionMesh) -> bool {
-        self.aabb.is_colliding(&mesh.aabb)
+        self.aabb.is_colliding(&mesh.aabb) && self.shape.is_colliding(&mesh.shape)
     }
 
     pub fn transform(&self, translation: glm::Vec2, scaling: f32) -> CollisionMesh {
-        let aabb = self.aabb * scaling + translation;
-        CollisionMesh { aabb }
+        CollisionMesh::new(self.shape.transform(translation, scaling))
     }
 
     pub fn closest_intersection_vector(&self, mesh: &CollisionMesh) -> glm::Vec2 {
-        self.aabb.closest_intersection_vector(&mesh.aabb)
+        self.shape.closest_intersection_vector(&mesh.shape)
     }
 }
 
@@ -0,0 +1,80 @@
+use serde::{Deserialize, Serialize};
+
+/// Categories of colliders, for choosing which collide with which.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CollisionLayer {
+    PLAYER,
+    ENEMY,
+    PROJECTILE,
+    PROP,
+    /// Harmless wildlife, like fireflies, which only bumps into the scenery.
+    AMBIENT,
+    SENSOR,
+    /// The triangles of the world collision mesh.
+    WORLD,
+}
+
+const COLLISION_LAYERS: [CollisionLayer; 7] = [
+    CollisionLayer::PLAYER,
+    CollisionLayer::ENEMY,
+    CollisionLayer::PROJECTILE,
+    CollisionLayer::PROP,
+    CollisionLayer::AMBIENT,
+    CollisionLayer::SENSOR,
+    CollisionLayer::WORLD,
+];
+
+impl CollisionLayer {
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+///
+/// A set of collision layers, stored as a bitmask with one bit per layer.
+/// In data, it is written as the list of the names of its layers.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(from = "Vec<CollisionLayer>", into = "Vec<CollisionLayer>")]
+pub struct CollisionLayers {
+    bits: u32,
+}
+
+impl CollisionLayers {
+    pub const ALL: CollisionLayers = CollisionLayers {
+        bits: (1 << COLLISION_LAYERS.len()) - 1,
+    };
+
+    pub fn contains(self, layer: CollisionLayer) -> bool {
+        self.bits & layer.bit() != 0
+    }
+
+    pub fn intersects(self, layers: CollisionLayers) -> bool {
+        self.bits & layers.bits != 0
+    }
+}
+
+impl From<CollisionLayer> for CollisionLayers {
+    fn from(layer: CollisionLayer) -> Self {
+        Self { bits: layer.bit() }
+    }
+}
+
+impl From<Vec<CollisionLayer>> for CollisionLayers {
+    fn from(layers: Vec<CollisionLayer>) -> Self {
+        Self {
+            bits: layers.iter().fold(0, |bits, layer| bits | layer.bit()),
+        }
+    }
+}
+
+impl From<CollisionLayers> for Vec<CollisionLayer> {
+    fn from(layers: CollisionLayers) -> Self {
+        COLLISION_LAYERS
+            .iter()
+            .copied()
+            .filter(|layer| layers.contains(*layer))
+            .collect()
+    }
+}
@@ -0,0 +1,345 @@
+use std::{collections::HashMap, path::Path};
+
+use glm::Vec2;
+
+/// Pixels at least this opaque are solid.
+const MIN_SOLID_ALPHA: u8 = 128;
+
+/// A simple polygon with holes traced from a mask, in pixel coordinates with y pointing down.
+pub struct TracedPolygon {
+    pub outline: Vec<Vec2>,
+    pub holes: Vec<Vec<Vec2>>,
+}
+
+///
+/// Which pixels of a mask image are solid, which are those that are mostly opaque.
+/// Everything outside the image is empty.
+///
+pub struct Mask {
+    width: u32,
+    height: u32,
+    solid: Vec<bool>,
+}
+
+impl Mask {
+    pub fn load(path: impl AsRef<Path>) -> image::ImageResult<Mask> {
+        let image = image::open(path)?.into_rgba8();
+        Ok(Mask {
+            width: image.width(),
+            height: image.height(),
+            solid: image
+                .pixels()
+                .map(|pixel| pixel[3] >= MIN_SOLID_ALPHA)
+                .collect(),
+        })
+    }
+
+    fn is_solid(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && (x as u32) < self.width
+            && (y as u32) < self.height
+            && self.solid[y as usize * self.width as usize + x as usize]
+    }
+
+    ///
+    /// Traces the outlines of the solid regions and the holes in them along the sides of the pixels,
+    /// then simplifies them so no corner that was left out is further than the tolerance from the result, in pixels.
+    /// Regions and holes smaller than the tolerance disappear. Pixels touching only at a corner are kept apart.
+    ///
+    pub fn trace(&self, tolerance: f32) -> Vec<TracedPolygon> {
+        let (outlines, holes): (Vec<Vec<Vec2>>, Vec<Vec<Vec2>>) = self
+            .trace_loops()
+            .into_iter()
+            .partition(|corners| signed_area(corners) > 0.0);
+
+        let mut polygons: Vec<TracedPolygon> = outlines
+            .iter()
+            .map(|outline| TracedPolygon {
+                outline: simplify(outline, tolerance),
+                holes: Vec::new(),
+            })
+            .collect();
+        for hole in holes.iter() {
+            // Holes belong to the smallest outline around them
+            let owner = outlines
+                .iter()
+                .enumerate()
+                .filter(|(_, outline)| is_inside_polygon(hole[0], outline))
+                .min_by(|(_, outline_1), (_, outline_2)| {
+                    signed_area(outline_1).total_cmp(&signed_area(outline_2))
+                });
+            if let Some((owner, _)) = owner {
+                polygons[owner].holes.push(simplify(hole, tolerance));
+            }
+        }
+
+        polygons.retain(|polygon| polygon.outline.len() >= 3);
+        for polygon in polygons.iter_mut() {
+            polygon.holes.retain(|hole| hole.len() >= 3);
+        }
+        polygons
+    }
+
+    ///
+    /// Follows the sides between solid and empty pixels into closed loops, keeping the solid pixels on the right.
+    /// With y pointing down, outlines then go around clockwise and holes counter clockwise.
+    ///
+    fn trace_loops(&self) -> Vec<Vec<Vec2>> {
+        // Sides as their start and end corners, in the order the pixels are scanned
+        let mut sides: Vec<((i32, i32), (i32, i32))> = Vec::new();
+        for y in 0..self.height as i32 {
+            for x in 0..self.width as i32 {
+                if !self.is_solid(x, y) {
+                    continue;
+                }
+                if !self.is_solid(x, y - 1) {
+                    sides.push(((x, y), (x + 1, y)));
+                }
+                if !self.is_solid(x + 1, y) {
+                    sides.push(((x + 1, y), (x + 1, y + 1)));
+                }
+                if !self.is_solid(x, y + 1) {
+                    sides.push(((x + 1, y + 1), (x, y + 1)));
+                }
+                if !self.is_solid(x - 1, y) {
+                    sides.push(((x, y + 1), (x, y)));
+                }
+            }
+        }
+        let mut sides_from: HashMap<(i32, i32), Vec<usize>> = HashMap::new();
+        for (index, (start, _)) in sides.iter().enumerate() {
+            sides_from.entry(*start).or_default().push(index);
+        }
+
+        let mut used = vec![false; sides.len()];
+        let mut loops = Vec::new();
+        for first in 0..sides.len() {
+            if used[first] {
+                continue;
+            }
+            let mut corners = Vec::new();
+            let mut side = first;
+            loop {
+                used[side] = true;
+                let (start, end) = sides[side];
+                corners.push(glm::vec2(start.0 as f32, start.1 as f32));
+
+                // Where two solid pixels only touch at a corner, turning right keeps them apart
+                let direction = (end.0 - start.0, end.1 - start.1);
+                let right = (-direction.1, direction.0);
+                let next = sides_from[&end]
+                    .iter()
+                    .copied()
+                    .filter(|next| !used[*next] || *next == first)
+                    .min_by_key(|next| {
+                        let (next_start, next_end) = sides[*next];
+                        let next_direction = (next_end.0 - next_start.0, next_end.1 - next_start.1);
+                        next_direction != right
+                    });
+                match next {
+                    Some(next) if next != first => side = next,
+                    _ => break,
+                }
+            }
+            loops.push(corners);
+        }
+        loops
+    }
+}
+
+/// Twice the area of the polygon, positive if its corners go around clockwise with y pointing down.
+fn signed_area(corners: &[Vec2]) -> f32 {
+    (0..corners.len())
+        .map(|index| {
+            let (a, b) = (corners[index], corners[(index + 1) % corners.len()]);
+            a.x * b.y - b.x * a.y
+        })
+        .sum()
+}
+
+/// Whether the point is inside the polygon, by counting how many of its sides a ray going right crosses.
+fn is_inside_polygon(point: Vec2, corners: &[Vec2]) -> bool {
+    let mut inside = false;
+    for index in 0..corners.len() {
+        let (a, b) = (corners[index], corners[(index + 1) % corners.len()]);
+        if (a.y > point.y) != (b.y > point.y)
+            && point.x < a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+///
+/// Simplifies a closed loop with the Ramer-Douglas-Peucker algorithm,
+/// splitting it in two at its top left corner and the corner furthest from it.
+///
+fn simplify(corners: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    // The top left corner is always a real corner, never one in the middle of a side
+    let top_left = (0..corners.len())
+        .min_by(|index_1, index_2| {
+            let (corner_1, corner_2) = (corners[*index_1], corners[*index_2]);
+            (corner_1.x, corner_1.y)
+                .partial_cmp(&(corner_2.x, corner_2.y))
+                .unwrap()
+        })
+        .unwrap_or(0);
+    let mut corners = corners.to_vec();
+    corners.rotate_left(top_left);
+
+    let furthest = (0..corners.len())
+        .max_by(|index_1, index_2| {
+            (corners[*index_1] - corners[0])
+                .norm_squared()
+                .total_cmp(&(corners[*index_2] - corners[0]).norm_squared())
+        })
+        .unwrap_or(0);
+    if furthest == 0 {
+        return Vec::new();
+    }
+
+    let mut closed = corners.clone();
+    closed.push(corners[0]);
+    let mut simplified = simplify_chain(&closed[..=furthest], tolerance);
+    simplified.pop();
+    simplified.extend(simplify_chain(&closed[furthest..], tolerance));
+    simplified.pop();
+    simplified
+}
+
+/// Keeps the ends of the chain, and the corners in between that stray further than the tolerance from it.
+fn simplify_chain(corners: &[Vec2], tolerance: f32) -> Vec<Vec2> {
+    let (start, end) = (corners[0], corners[corners.len() - 1]);
+    // Chains that come back to where they started measure from their start instead
+    let distance = |corner: Vec2| {
+        let offset = corner - start;
+        if start == end {
+            return offset.norm();
+        }
+        let direction = (end - start).normalize();
+        (offset.x * direction.y - offset.y * direction.x).abs()
+    };
+    let furthest = (1..corners.len() - 1).max_by(|index_1, index_2| {
+        distance(corners[*index_1]).total_cmp(&distance(corners[*index_2]))
+    });
+
+    match furthest {
+        Some(furthest) if distance(corners[furthest]) > tolerance => {
+            let mut simplified = simplify_chain(&corners[..=furthest], tolerance);
+            simplified.pop();
+            simplified.extend(simplify_chain(&corners[furthest..], tolerance));
+            simplified
+        }
+        _ => vec![start, end],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mask drawn as rows of text, with `#` for solid pixels.
+    fn mask(rows: &[&str]) -> Mask {
+        Mask {
+            width: rows[0].len() as u32,
+            height: rows.len() as u32,
+            solid: rows
+                .iter()
+                .flat_map(|row| row.chars().map(|pixel| pixel == '#'))
+                .collect(),
+        }
+    }
+
+    fn corners(points: &[(f32, f32)]) -> Vec<Vec2> {
+        points.iter().map(|&(x, y)| glm::vec2(x, y)).collect()
+    }
+
+    #[test]
+    fn filled_squares_trace_to_their_corners() {
+        let mask = mask(&["##", "##"]);
+        // Before simplifying, every pixel side is a side of the loop
+        assert_eq!(mask.trace_loops()[0].len(), 8);
+
+        let polygons = mask.trace(0.5);
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(
+            polygons[0].outline,
+            corners(&[(0.0, 0.0), (2.0, 0.0), (2.0, 2.0), (0.0, 2.0)])
+        );
+        assert!(signed_area(&polygons[0].outline) > 0.0);
+        assert!(polygons[0].holes.is_empty());
+    }
+
+    #[test]
+    fn holes_go_around_the_other_way_inside_their_outline() {
+        let polygons = mask(&["###", "#.#", "###"]).trace(0.5);
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(
+            polygons[0].outline,
+            corners(&[(0.0, 0.0), (3.0, 0.0), (3.0, 3.0), (0.0, 3.0)])
+        );
+        assert_eq!(
+            polygons[0].holes,
+            [corners(&[(1.0, 1.0), (1.0, 2.0), (2.0, 2.0), (2.0, 1.0)])]
+        );
+        assert!(signed_area(&polygons[0].holes[0]) < 0.0);
+    }
+
+    #[test]
+    fn pixels_touching_at_a_corner_are_kept_apart() {
+        let polygons = mask(&["#.", ".#"]).trace(0.5);
+        assert_eq!(polygons.len(), 2);
+        assert_eq!(
+            polygons[0].outline,
+            corners(&[(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)])
+        );
+        assert_eq!(
+            polygons[1].outline,
+            corners(&[(1.0, 1.0), (2.0, 1.0), (2.0, 2.0), (1.0, 2.0)])
+        );
+
+        // Which joins empty pixels touching at a corner into one hole, going through the corner twice
+        let polygons = mask(&["####", "#.##", "##.#", "####"]).trace(0.0);
+        assert_eq!(polygons.len(), 1);
+        assert_eq!(polygons[0].holes.len(), 1);
+        let hole = &polygons[0].holes[0];
+        assert_eq!(hole.len(), 8);
+        let shared_corner = glm::vec2(2.0, 2.0);
+        assert_eq!(
+            hole.iter()
+                .filter(|corner| **corner == shared_corner)
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn simplifying_drops_corners_within_the_tolerance() {
+        let bumpy = corners(&[
+            (0.0, 0.0),
+            (5.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 4.0),
+            (5.0, 4.4),
+            (0.0, 4.0),
+        ]);
+        assert_eq!(
+            simplify(&bumpy, 0.5),
+            corners(&[(0.0, 0.0), (10.0, 0.0), (10.0, 4.0), (0.0, 4.0)])
+        );
+        // Corners in the middle of straight sides go even without any tolerance
+        assert_eq!(
+            simplify(&bumpy, 0.0),
+            corners(&[(0.0, 0.0), (10.0, 0.0), (10.0, 4.0), (5.0, 4.4), (0.0, 4.0)])
+        );
+
+        // A staircase becomes its diagonal, and regions smaller than the tolerance disappear
+        assert_eq!(
+            mask(&["#..", "##.", "###"]).trace(1.0)[0].outline,
+            corners(&[(0.0, 0.0), (3.0, 3.0), (0.0, 3.0)])
+        );
+        assert!(mask(&["#"]).trace(1.0).is_empty());
+    }
+}
@@ -1,3 +1,9 @@
+mod broadphase;
+pub use broadphase::SpatialHash;
+
+mod bvh;
+pub use bvh::Bvh;
+
 mod collision_mesh;
 pub use collision_mesh::{CollisionMesh, CollisionMeshIdentifier, CollisionMeshManager};
 
@@ -7,7 +13,25 @@ pub use aabb::Aabb;
 mod triangle;
 pub use triangle::Triangle;
 
-pub mod ray;
+mod layers;
+pub use layers::{CollisionLayer, CollisionLayers};
+
+mod mask;
+pub use mask::Mask;
+
+mod shape;
+pub use shape::Shape;
+
+mod ray;
+pub use ray::{Ray, RayHit};
+
+mod sweep;
+
+mod triangulation;
+pub use triangulation::triangulate;
 
 mod world_collision_mesh;
-pub use world_collision_mesh::WorldCollisionMesh;
\ No newline at end of file
+pub use world_collision_mesh::WorldCollisionMesh;
+
+mod world_surfaces;
+pub use world_surfaces::{SurfaceMaterial, WorldSurfaces};
\ No newline at end of file
@@ -1,11 +1,32 @@
 use glm::Vec2;
 
-#[derive(Debug)]
+/// Below this, a ray is considered parallel to an axis or a line.
+const MIN_DIRECTION: f32 = 0.00001;
+
+///
+/// A line segment from the start point to the start point plus the vector.
+/// Points along it are given by the fraction of the vector travelled, from 0 to 1.
+///
+#[derive(Debug, Clone, Copy)]
 pub struct Ray {
     start_point: Vec2,
     vector: Vec2,
 }
 
+/// Where a ray first enters something.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    /// Fraction of the ray's vector travelled before the hit.
+    pub time: f32,
+    pub point: Vec2,
+    /// Normal of the surface that was hit, pointing out of it.
+    pub normal: Vec2,
+}
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
 impl Ray {
     pub fn new(start_point: Vec2, vector: Vec2) -> Self {
         Self {
@@ -14,14 +35,42 @@ impl Ray {
         }
     }
 
-    pub fn are_intersecting(ray_1: &Ray, ray_2: &Ray) -> bool {
-        let p = ray_1.start_point;
-        let q = ray_2.start_point;
-        let v = ray_1.vector;
-        let u = ray_2.vector;
+    pub fn start_point(&self) -> Vec2 {
+        self.start_point
+    }
+
+    pub fn vector(&self) -> Vec2 {
+        self.vector
+    }
 
-        let t = u.x * u.y * (q.y + p.x - p.y - q.x) / (v.y * u.x - v.x * u.y);
-        let s = (p.x - q.x + t * v.x) / u.x;
-        return t >= 0.0 && t <= 1.0 && s >= 0.0 && s <= 1.0;
+    pub fn point_at(&self, time: f32) -> Vec2 {
+        self.start_point + self.vector * time
+    }
+
+    pub fn hit(&self, time: f32, normal: Vec2) -> RayHit {
+        RayHit {
+            time,
+            point: self.point_at(time),
+            normal,
+        }
+    }
+
+    ///
+    /// Returns how far along each of the rays they cross, unless they don't.
+    /// Parallel rays never cross.
+    ///
+    pub fn intersection(ray_1: &Ray, ray_2: &Ray) -> Option<(f32, f32)> {
+        let denominator = cross(ray_1.vector, ray_2.vector);
+        if denominator.abs() < MIN_DIRECTION {
+            return None;
+        }
+        let offset = ray_2.start_point - ray_1.start_point;
+        let t = cross(offset, ray_2.vector) / denominator;
+        let s = cross(offset, ray_1.vector) / denominator;
+        if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&s) {
+            Some((t, s))
+        } else {
+            None
+        }
     }
 }
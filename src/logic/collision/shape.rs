@@ -0,0 +1,515 @@
+use glm::Vec2;
+
+use super::{Aabb, Ray, RayHit};
+
+///
+/// The shape of a collision mesh.
+///
+/// Every shape is a convex core, made of its corners, grown outwards by a radius:
+/// a circle is a point grown by its radius and a capsule is a line segment grown by its radius.
+/// That way any pair of shapes can be tested with the separating axis theorem.
+///
+#[derive(Clone, Debug)]
+pub enum Shape {
+    Aabb(Aabb),
+    /// A convex polygon with its corners in counter clockwise order.
+    Polygon(Vec<Vec2>),
+    Circle {
+        center: Vec2,
+        radius: f32,
+    },
+    /// Every point within the radius of the line segment from start to end.
+    Capsule {
+        start: Vec2,
+        end: Vec2,
+        radius: f32,
+    },
+}
+
+/// Axes closer to zero length than this can't separate anything.
+const MIN_AXIS_LENGTH: f32 = 0.00001;
+
+impl Shape {
+    /// The corners of the convex core of the shape, and how far the shape reaches out from the core.
+    fn core(&self) -> (Vec<Vec2>, f32) {
+        match self {
+            Shape::Aabb(aabb) => (aabb.corners(), 0.0),
+            Shape::Polygon(corners) => (corners.clone(), 0.0),
+            Shape::Circle { center, radius } => (vec![*center], *radius),
+            Shape::Capsule { start, end, radius } => (vec![*start, *end], *radius),
+        }
+    }
+
+    pub fn transform(&self, translation: Vec2, scaling: f32) -> Shape {
+        let transform = |point: &Vec2| point * scaling + translation;
+        match self {
+            Shape::Aabb(aabb) => Shape::Aabb(*aabb * scaling + translation),
+            Shape::Polygon(corners) => Shape::Polygon(corners.iter().map(transform).collect()),
+            Shape::Circle { center, radius } => Shape::Circle {
+                center: transform(center),
+                radius: radius * scaling,
+            },
+            Shape::Capsule { start, end, radius } => Shape::Capsule {
+                start: transform(start),
+                end: transform(end),
+                radius: radius * scaling,
+            },
+        }
+    }
+
+    pub fn surrounding_aabb(&self) -> Aabb {
+        if let Shape::Aabb(aabb) = self {
+            return *aabb;
+        }
+        let (corners, radius) = self.core();
+        let corner_aabbs: Vec<Aabb> = corners
+            .iter()
+            .map(|corner| Aabb {
+                min_x: corner.x - radius,
+                min_y: corner.y - radius,
+                max_x: corner.x + radius,
+                max_y: corner.y + radius,
+            })
+            .collect();
+        Aabb::surrounding_aabb(&corner_aabbs)
+    }
+
+    pub fn is_colliding(&self, shape: &Shape) -> bool {
+        match (self, shape) {
+            (Shape::Aabb(aabb_1), Shape::Aabb(aabb_2)) => aabb_1.is_colliding(aabb_2),
+            _ => self.minimum_translation_vector(shape).is_some(),
+        }
+    }
+
+    ///
+    /// Returns the shortest vector that moves this shape out of the other one.
+    /// Shapes that don't collide don't need to move at all.
+    ///
+    pub fn closest_intersection_vector(&self, shape: &Shape) -> Vec2 {
+        match (self, shape) {
+            (Shape::Aabb(aabb_1), Shape::Aabb(aabb_2)) => {
+                aabb_1.closest_intersection_vector(aabb_2)
+            }
+            _ => self
+                .minimum_translation_vector(shape)
+                .unwrap_or_else(|| glm::vec2(0.0, 0.0)),
+        }
+    }
+
+    fn minimum_translation_vector(&self, shape: &Shape) -> Option<Vec2> {
+        let (corners_1, radius_1) = self.core();
+        let (corners_2, radius_2) = shape.core();
+        minimum_translation_vector(&corners_1, radius_1, &corners_2, radius_2)
+    }
+
+    ///
+    /// Returns where the ray first enters the shape.
+    /// Rays starting inside the shape don't hit it.
+    ///
+    pub fn cast_ray(&self, ray: &Ray) -> Option<RayHit> {
+        match self {
+            Shape::Aabb(aabb) => aabb.cast_ray(ray),
+            _ => {
+                let (corners, radius) = self.core();
+                cast_ray_at_core(ray, &corners, radius)
+            }
+        }
+    }
+
+    ///
+    /// Returns when this shape, moving by the displacement, first touches the other shape,
+    /// as a fraction of the displacement. Shapes that already overlap at the start don't have a time of impact.
+    ///
+    /// The shapes touch when the displacement, as a ray from the origin, enters their Minkowski difference,
+    /// which is the other shape's core minus this shape's core, grown by both their radii.
+    ///
+    pub fn time_of_impact(&self, displacement: Vec2, shape: &Shape) -> Option<f32> {
+        if let (Shape::Aabb(aabb_1), Shape::Aabb(aabb_2)) = (self, shape) {
+            return aabb_1.time_of_impact(displacement, aabb_2);
+        }
+        let (corners_1, radius_1) = self.core();
+        let (corners_2, radius_2) = shape.core();
+        let differences = corners_2
+            .iter()
+            .flat_map(|corner_2| corners_1.iter().map(move |corner_1| corner_2 - corner_1))
+            .collect();
+        cast_ray_at_core(
+            &Ray::new(glm::vec2(0.0, 0.0), displacement),
+            &convex_hull(differences),
+            radius_1 + radius_2,
+        )
+        .map(|hit| hit.time)
+    }
+}
+
+///
+/// Tests two convex cores grown by their radii against each other with the separating axis theorem,
+/// returning the shortest vector that moves the first out of the second if they overlap.
+///
+/// The axes tested are the normals of the sides of both cores, and, for rounded shapes,
+/// the directions between every pair of corners, which is where rounded shapes meet.
+///
+fn minimum_translation_vector(
+    corners_1: &[Vec2],
+    radius_1: f32,
+    corners_2: &[Vec2],
+    radius_2: f32,
+) -> Option<Vec2> {
+    // Testing along x and y too also separates circles sharing their center
+    let mut axes: Vec<Vec2> = vec![glm::vec2(1.0, 0.0), glm::vec2(0.0, 1.0)];
+    axes.extend(side_normals(corners_1).chain(side_normals(corners_2)));
+    if radius_1 > 0.0 || radius_2 > 0.0 {
+        for corner_1 in corners_1.iter() {
+            axes.extend(corners_2.iter().map(|corner_2| corner_1 - corner_2));
+        }
+    }
+
+    let mut translation: Option<Vec2> = None;
+    for axis in axes {
+        if axis.norm() < MIN_AXIS_LENGTH {
+            continue;
+        }
+        let axis = axis.normalize();
+        let (min_1, max_1) = project(corners_1, radius_1, &axis);
+        let (min_2, max_2) = project(corners_2, radius_2, &axis);
+        if max_1 <= min_2 || max_2 <= min_1 {
+            return None;
+        }
+
+        let forwards = max_2 - min_1;
+        let backwards = max_1 - min_2;
+        let axis_translation = if forwards < backwards {
+            axis * forwards
+        } else {
+            -axis * backwards
+        };
+        if translation
+            .is_none_or(|translation| axis_translation.norm_squared() < translation.norm_squared())
+        {
+            translation = Some(axis_translation);
+        }
+    }
+
+    translation
+}
+
+/// The sides of a convex core as their start and end, going around it counter clockwise.
+fn sides(corners: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    let sides = match corners.len() {
+        0 | 1 => 0,
+        corners => corners,
+    };
+    (0..sides).map(move |index| (corners[index], corners[(index + 1) % corners.len()]))
+}
+
+///
+/// Returns where the ray first enters a convex core grown by the radius,
+/// which is through one of its sides pushed out by the radius, or one of the circles around its corners.
+/// Rays starting inside don't hit it.
+///
+fn cast_ray_at_core(ray: &Ray, corners: &[Vec2], radius: f32) -> Option<RayHit> {
+    if minimum_translation_vector(corners, radius, &[ray.start_point()], 0.0).is_some() {
+        return None;
+    }
+    let side_hits = sides(corners).filter_map(|(start, end)| {
+        let side = end - start;
+        if side.norm() < MIN_AXIS_LENGTH {
+            return None;
+        }
+        // Rays only enter through sides they run against
+        let normal = glm::vec2(side.y, -side.x).normalize();
+        if ray.vector().dot(&normal) >= 0.0 {
+            return None;
+        }
+        let (time, _) = Ray::intersection(ray, &Ray::new(start + normal * radius, side))?;
+        Some(ray.hit(time, normal))
+    });
+    let corner_hits = corners
+        .iter()
+        .filter(|_| radius > 0.0)
+        .filter_map(|corner| cast_ray_at_circle(ray, *corner, radius));
+    side_hits.chain(corner_hits).reduce(|hit_1, hit_2| {
+        if hit_2.time < hit_1.time {
+            hit_2
+        } else {
+            hit_1
+        }
+    })
+}
+
+/// Returns where the ray first enters the circle. Rays starting inside don't hit it.
+fn cast_ray_at_circle(ray: &Ray, center: Vec2, radius: f32) -> Option<RayHit> {
+    let offset = ray.start_point() - center;
+    let vector = ray.vector();
+    let length_squared = vector.norm_squared();
+    if length_squared < MIN_AXIS_LENGTH * MIN_AXIS_LENGTH {
+        return None;
+    }
+    // Where the distance to the center is the radius, solved for the time along the ray
+    let half_b = offset.dot(&vector);
+    let c = offset.norm_squared() - radius * radius;
+    let discriminant = half_b * half_b - length_squared * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+    let time = (-half_b - discriminant.sqrt()) / length_squared;
+    if !(0.0..=1.0).contains(&time) {
+        return None;
+    }
+    Some(ray.hit(time, (ray.point_at(time) - center) / radius))
+}
+
+///
+/// The corners of the smallest convex polygon around the points, counter clockwise,
+/// leaving out corners in the middle of its sides.
+/// Points all on one line give the ends of the line.
+///
+fn convex_hull(mut points: Vec<Vec2>) -> Vec<Vec2> {
+    points.sort_by(|point_1, point_2| {
+        (point_1.x, point_1.y)
+            .partial_cmp(&(point_2.x, point_2.y))
+            .unwrap()
+    });
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+
+    // The lower half of the hull going right, then the upper half going back left
+    let mut hull: Vec<Vec2> = Vec::with_capacity(points.len() + 1);
+    for half in [points.clone(), points.into_iter().rev().collect()] {
+        let half_start = hull.len();
+        for point in half {
+            while hull.len() >= half_start + 2 {
+                let (a, b) = (hull[hull.len() - 2], hull[hull.len() - 1]);
+                let turn = (b - a).x * (point - b).y - (b - a).y * (point - b).x;
+                if turn > 0.0 {
+                    break;
+                }
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        hull.pop();
+    }
+    hull
+}
+
+/// Normals of the sides of a convex core, which point in either direction.
+fn side_normals(corners: &[Vec2]) -> impl Iterator<Item = Vec2> + '_ {
+    let sides = match corners.len() {
+        0 | 1 => 0,
+        2 => 1,
+        corners => corners,
+    };
+    (0..sides).map(move |index| {
+        let side = corners[(index + 1) % corners.len()] - corners[index];
+        glm::vec2(side.y, -side.x)
+    })
+}
+
+fn project(corners: &[Vec2], radius: f32, axis: &Vec2) -> (f32, f32) {
+    let (min, max) = corners.iter().map(|corner| corner.dot(axis)).fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(min, max), projection| (min.min(projection), max.max(projection)),
+    );
+    (min - radius, max + radius)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aabb(min_x: f32, min_y: f32, max_x: f32, max_y: f32) -> Shape {
+        Shape::Aabb(Aabb {
+            min_x,
+            min_y,
+            max_x,
+            max_y,
+        })
+    }
+
+    /// A square standing on its corner, reaching one unit from its center.
+    fn diamond(center: Vec2) -> Shape {
+        Shape::Polygon(
+            [(1.0, 0.0), (0.0, 1.0), (-1.0, 0.0), (0.0, -1.0)]
+                .iter()
+                .map(|&(x, y)| center + glm::vec2(x, y))
+                .collect(),
+        )
+    }
+
+    fn circle(x: f32, y: f32, radius: f32) -> Shape {
+        Shape::Circle {
+            center: glm::vec2(x, y),
+            radius,
+        }
+    }
+
+    fn capsule(start: (f32, f32), end: (f32, f32), radius: f32) -> Shape {
+        Shape::Capsule {
+            start: glm::vec2(start.0, start.1),
+            end: glm::vec2(end.0, end.1),
+            radius,
+        }
+    }
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).norm() < 0.0001,
+            "{:?} isn't {:?}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn minimum_translation_vector_for_every_pair_of_shapes() {
+        let push_left = glm::vec2(-0.5, 0.0);
+        let pairs = [
+            (
+                aabb(0.0, 0.0, 2.0, 2.0),
+                aabb(1.5, 0.0, 3.5, 2.0),
+                push_left,
+            ),
+            (
+                aabb(0.0, 0.0, 2.0, 2.0),
+                diamond(glm::vec2(2.5, 1.0)),
+                push_left,
+            ),
+            (aabb(0.0, 0.0, 2.0, 2.0), circle(2.5, 1.0, 1.0), push_left),
+            (
+                aabb(0.0, 0.0, 2.0, 2.0),
+                capsule((3.0, 1.0), (5.0, 1.0), 1.5),
+                push_left,
+            ),
+            (
+                diamond(glm::vec2(0.0, 0.0)),
+                diamond(glm::vec2(0.75, 0.75)),
+                glm::vec2(-0.25, -0.25),
+            ),
+            (
+                diamond(glm::vec2(0.0, 0.0)),
+                circle(1.5, 0.0, 1.0),
+                push_left,
+            ),
+            (
+                diamond(glm::vec2(0.0, 0.0)),
+                capsule((1.5, 0.0), (3.0, 0.0), 1.0),
+                push_left,
+            ),
+            (circle(0.0, 0.0, 1.0), circle(1.5, 0.0, 1.0), push_left),
+            (
+                circle(0.0, 0.0, 1.0),
+                capsule((1.5, -1.0), (1.5, 1.0), 1.0),
+                push_left,
+            ),
+            (
+                capsule((-2.0, 0.0), (0.0, 0.0), 1.0),
+                capsule((1.5, -1.0), (1.5, 1.0), 1.0),
+                push_left,
+            ),
+        ];
+        for (shape_1, shape_2, translation) in pairs {
+            assert!(shape_1.is_colliding(&shape_2));
+            assert_near(shape_1.closest_intersection_vector(&shape_2), translation);
+            assert_near(shape_2.closest_intersection_vector(&shape_1), -translation);
+        }
+    }
+
+    #[test]
+    fn rounded_shapes_miss_the_corners_of_their_aabbs() {
+        let square = aabb(0.0, 0.0, 2.0, 2.0);
+        let circle = circle(2.6, 2.6, 0.8);
+        assert!(square.is_colliding(&Shape::Aabb(circle.surrounding_aabb())));
+        assert!(!square.is_colliding(&circle));
+        assert!(!circle.is_colliding(&square));
+    }
+
+    #[test]
+    fn rays_enter_every_shape() {
+        let from_left = Ray::new(glm::vec2(-5.0, 1.0), glm::vec2(10.0, 0.0));
+        for shape in [
+            aabb(0.0, 0.0, 2.0, 2.0),
+            circle(1.0, 1.0, 1.0),
+            capsule((1.0, 1.0), (3.0, 1.0), 1.0),
+        ] {
+            let hit = shape.cast_ray(&from_left).unwrap();
+            assert!((hit.time - 0.5).abs() < 0.0001);
+            assert_near(hit.point, glm::vec2(0.0, 1.0));
+            assert_near(hit.normal, glm::vec2(-1.0, 0.0));
+        }
+
+        let hit = diamond(glm::vec2(1.0, 1.0))
+            .cast_ray(&Ray::new(glm::vec2(-5.0, 1.5), glm::vec2(10.0, 0.0)))
+            .unwrap();
+        assert!((hit.time - 0.55).abs() < 0.0001);
+        assert_near(hit.normal, glm::vec2(-1.0, 1.0).normalize());
+
+        // Through the side of the capsule rather than around one of its ends
+        let hit = capsule((1.0, 1.0), (3.0, 1.0), 1.0)
+            .cast_ray(&Ray::new(glm::vec2(2.0, 5.0), glm::vec2(0.0, -10.0)))
+            .unwrap();
+        assert!((hit.time - 0.3).abs() < 0.0001);
+        assert_near(hit.normal, glm::vec2(0.0, 1.0));
+    }
+
+    #[test]
+    fn rays_miss_the_corners_of_rounded_shapes_and_start_inside_without_hitting() {
+        let circle = circle(1.0, 1.0, 1.0);
+        let past_corner = Ray::new(glm::vec2(-1.0, 1.4), glm::vec2(2.0, -2.0));
+        assert!(circle.surrounding_aabb().cast_ray(&past_corner).is_some());
+        assert!(circle.cast_ray(&past_corner).is_none());
+
+        let from_inside = Ray::new(glm::vec2(1.0, 1.0), glm::vec2(10.0, 0.0));
+        assert!(circle.cast_ray(&from_inside).is_none());
+        assert!(diamond(glm::vec2(1.0, 1.0))
+            .cast_ray(&from_inside)
+            .is_none());
+        assert!(capsule((1.0, 1.0), (3.0, 1.0), 0.5)
+            .cast_ray(&from_inside)
+            .is_none());
+    }
+
+    #[test]
+    fn time_of_impact_for_every_shape() {
+        let right = glm::vec2(10.0, 0.0);
+        let cases = [
+            (aabb(0.0, 0.0, 1.0, 1.0), aabb(3.0, 0.0, 4.0, 1.0), 0.2),
+            (circle(0.0, 0.0, 1.0), circle(5.0, 0.0, 1.0), 0.3),
+            (
+                diamond(glm::vec2(0.0, 0.0)),
+                diamond(glm::vec2(5.0, 0.5)),
+                0.35,
+            ),
+            (
+                capsule((0.0, 0.0), (0.0, 2.0), 0.5),
+                Shape::Polygon(vec![
+                    glm::vec2(3.0, 0.0),
+                    glm::vec2(5.0, 0.0),
+                    glm::vec2(5.0, 2.0),
+                    glm::vec2(3.0, 2.0),
+                ]),
+                0.25,
+            ),
+            // Touching the corner of the square later than its surrounding aabb would
+            (circle(-3.0, 2.4, 0.5), aabb(0.0, 0.0, 2.0, 2.0), 0.27),
+        ];
+        for (moving, fixed, expected) in cases {
+            let time_of_impact = moving.time_of_impact(right, &fixed).unwrap();
+            assert!(
+                (time_of_impact - expected).abs() < 0.0001,
+                "{} isn't {}",
+                time_of_impact,
+                expected
+            );
+        }
+
+        assert!(circle(0.0, 0.0, 1.0)
+            .time_of_impact(right, &circle(5.0, 2.5, 1.0))
+            .is_none());
+        assert!(circle(0.0, 0.0, 1.0)
+            .time_of_impact(right, &circle(1.0, 0.0, 1.0))
+            .is_none());
+    }
+}
@@ -0,0 +1,63 @@
+use glm::Vec2;
+
+/// Below this, a shape is considered not to move along an axis at all.
+const MIN_SPEED: f32 = 0.00001;
+
+fn project(corners: &[Vec2], axis: &Vec2) -> (f32, f32) {
+    corners.iter().map(|corner| corner.dot(axis)).fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(min, max), projection| (min.min(projection), max.max(projection)),
+    )
+}
+
+///
+/// Finds when a convex shape moving by the displacement first touches a fixed convex shape,
+/// as a fraction of the displacement between 0 and 1.
+///
+/// Both shapes are given by their corners, and the axes must include the normals of all their sides.
+/// Shapes that already overlap at the start, or never touch along the way, don't have a time of impact.
+///
+pub fn time_of_impact(
+    moving: &[Vec2],
+    displacement: Vec2,
+    fixed: &[Vec2],
+    axes: &[Vec2],
+) -> Option<f32> {
+    let mut enter_time = f32::NEG_INFINITY;
+    let mut exit_time = f32::INFINITY;
+    for axis in axes {
+        let (moving_min, moving_max) = project(moving, axis);
+        let (fixed_min, fixed_max) = project(fixed, axis);
+        let speed = displacement.dot(axis);
+        if speed.abs() < MIN_SPEED {
+            if moving_max <= fixed_min || moving_min >= fixed_max {
+                // Separated along this axis the whole way
+                return None;
+            }
+            continue;
+        }
+
+        let (axis_enter_time, axis_exit_time) = if speed > 0.0 {
+            (
+                (fixed_min - moving_max) / speed,
+                (fixed_max - moving_min) / speed,
+            )
+        } else {
+            (
+                (fixed_max - moving_min) / speed,
+                (fixed_min - moving_max) / speed,
+            )
+        };
+        enter_time = enter_time.max(axis_enter_time);
+        exit_time = exit_time.min(axis_exit_time);
+        if enter_time > exit_time {
+            return None;
+        }
+    }
+
+    if (0.0..=1.0).contains(&enter_time) {
+        Some(enter_time)
+    } else {
+        None
+    }
+}
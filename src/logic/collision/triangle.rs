@@ -1,10 +1,10 @@
 use glm::Vec2;
 
-use super::{aabb::AabbCorner, Aabb};
+use super::{aabb::AabbCorner, sweep, Aabb, Ray, RayHit};
 use strum::IntoEnumIterator;
 use strum_macros::EnumIter;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Triangle {
     a: Vec2,
     b: Vec2,
@@ -48,14 +48,13 @@ pub enum TriangleCorner {
 }
 
 impl Triangle {
+    /// Corners given clockwise are put in counter clockwise order, which the collision tests rely on.
     pub fn new(a: Vec2, b: Vec2, c: Vec2) -> Self {
         let mut b = b;
         let mut c = c;
         if (a.x * b.y - b.x * a.y) + (b.x * c.y - c.x * b.y) + (c.x * a.y - c.y * a.x) <= 0.0 {
-            println!("Triangles must be counter clockwise!");
             std::mem::swap(&mut b, &mut c);
         }
-        // TODO: MAKE SURE TRIANGLES ARE ALWAYS GIVEN COUNTERCLOCKWISE
         let min_x = *[a.x, b.x, c.x]
             .iter()
             .reduce(|k, l| if k < l { k } else { l })
@@ -86,6 +85,10 @@ impl Triangle {
         }
     }
 
+    pub fn surrounding_aabb(&self) -> &Aabb {
+        &self.surrounding_aabb
+    }
+
     pub fn closest_intersection_vector(&self, aabb: &Aabb) -> Vec2 {
         let mut min_distance = f32::INFINITY;
         let mut closest_vector = None;
@@ -121,6 +124,52 @@ impl Triangle {
         aabb.closest_intersection_vector(&self.surrounding_aabb)
     }
 
+    ///
+    /// Returns when the AABB, moving by the displacement, first touches this triangle,
+    /// as a fraction of the displacement.
+    ///
+    pub fn time_of_impact(&self, aabb: &Aabb, displacement: Vec2) -> Option<f32> {
+        sweep::time_of_impact(
+            &aabb.corners(),
+            displacement,
+            &[self.a, self.b, self.c],
+            &[
+                glm::vec2(1.0, 0.0),
+                glm::vec2(0.0, 1.0),
+                self.vector_to_side(TriangleSide::Ab),
+                self.vector_to_side(TriangleSide::Bc),
+                self.vector_to_side(TriangleSide::Ca),
+            ],
+        )
+    }
+
+    ///
+    /// Returns where the ray first enters this triangle.
+    /// Rays starting inside the triangle don't hit it.
+    ///
+    pub fn cast_ray(&self, ray: &Ray) -> Option<RayHit> {
+        TriangleSide::iter()
+            .filter_map(|side| {
+                let normal = self.vector_to_side(side);
+                // Rays only enter through sides they run against
+                if ray.vector().dot(&normal) >= 0.0 {
+                    return None;
+                }
+                let start_point = self.get_corner(side.get_start_corner());
+                let end_point = self.get_corner(side.get_end_corner());
+                let (time, _) =
+                    Ray::intersection(ray, &Ray::new(start_point, end_point - start_point))?;
+                Some(ray.hit(time, normal))
+            })
+            .reduce(|hit_1, hit_2| {
+                if hit_2.time < hit_1.time {
+                    hit_2
+                } else {
+                    hit_1
+                }
+            })
+    }
+
     pub fn is_point_inside(&self, point: &Vec2) -> bool {
         for side in TriangleSide::iter() {
             let start_point = self.get_corner(side.get_start_corner());
@@ -149,7 +198,7 @@ impl Triangle {
             {
                 // Left side of rectangle
                 let t = (aabb.min_x - start_point.x) / (direction_vector.x);
-                if t >= 0.0 && t <= 1.0 {
+                if (0.0..=1.0).contains(&t) {
                     let collision_y = start_point.y + t * direction_vector.y;
                     if collision_y >= aabb.min_y && collision_y <= aabb.max_y {
                         return true;
@@ -159,7 +208,7 @@ impl Triangle {
             {
                 // Right side of rectangle
                 let t = (aabb.max_x - start_point.x) / (direction_vector.x);
-                if t >= 0.0 && t <= 1.0 {
+                if (0.0..=1.0).contains(&t) {
                     let collision_y = start_point.y + t * direction_vector.y;
                     if collision_y >= aabb.min_y && collision_y <= aabb.max_y {
                         return true;
@@ -169,7 +218,7 @@ impl Triangle {
             {
                 // Bottom side of rectangle
                 let t = (aabb.min_y - start_point.y) / (direction_vector.y);
-                if t >= 0.0 && t <= 1.0 {
+                if (0.0..=1.0).contains(&t) {
                     let collision_x = start_point.x + t * direction_vector.x;
                     if collision_x >= aabb.min_x && collision_x <= aabb.max_x {
                         return true;
@@ -179,7 +228,7 @@ impl Triangle {
             {
                 // Top side of rectangle
                 let t = (aabb.max_y - start_point.y) / (direction_vector.y);
-                if t >= 0.0 && t <= 1.0 {
+                if (0.0..=1.0).contains(&t) {
                     let collision_x = start_point.x + t * direction_vector.x;
                     if collision_x >= aabb.min_x && collision_x <= aabb.max_x {
                         return true;
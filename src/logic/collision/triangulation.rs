@@ -0,0 +1,374 @@
+use glm::Vec2;
+
+use super::{Ray, Triangle};
+
+/// Below this, three corners are considered to lie on one line.
+const MIN_TURN: f32 = 0.00001;
+
+fn cross(a: Vec2, b: Vec2) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Twice the area of the polygon, positive if its corners are counter clockwise.
+fn signed_area(corners: &[Vec2]) -> f32 {
+    (0..corners.len())
+        .map(|index| cross(corners[index], corners[(index + 1) % corners.len()]))
+        .sum()
+}
+
+/// How much the path turns left at b, going from a through b to c, negative for right turns.
+fn turn(a: Vec2, b: Vec2, c: Vec2) -> f32 {
+    cross(b - a, c - b)
+}
+
+/// Whether the point is inside the triangle or on its sides, whichever way its corners go around.
+fn is_inside_triangle(point: Vec2, a: Vec2, b: Vec2, c: Vec2) -> bool {
+    let sides = [
+        cross(b - a, point - a),
+        cross(c - b, point - b),
+        cross(a - c, point - c),
+    ];
+    !(sides.iter().any(|side| *side < 0.0) && sides.iter().any(|side| *side > 0.0))
+}
+
+/// The side of the polygon going from the corner at the index to the next one.
+fn side(corners: &[Vec2], index: usize) -> Ray {
+    let start = corners[index];
+    Ray::new(start, corners[(index + 1) % corners.len()] - start)
+}
+
+/// Whether any two sides of the polygon that aren't next to each other cross or touch.
+fn crosses_itself(corners: &[Vec2]) -> bool {
+    (0..corners.len()).any(|index_1| {
+        (index_1 + 2..corners.len())
+            .filter(|index_2| (index_2 + 1) % corners.len() != index_1)
+            .any(|index_2| {
+                Ray::intersection(&side(corners, index_1), &side(corners, index_2)).is_some()
+            })
+    })
+}
+
+/// Whether any side of one polygon crosses or touches any side of the other.
+fn touch(corners_1: &[Vec2], corners_2: &[Vec2]) -> bool {
+    (0..corners_1.len()).any(|index_1| {
+        (0..corners_2.len()).any(|index_2| {
+            Ray::intersection(&side(corners_1, index_1), &side(corners_2, index_2)).is_some()
+        })
+    })
+}
+
+/// Whether the point is inside the polygon, going by how many of its sides a ray going right from the point crosses.
+fn is_inside_polygon(point: Vec2, corners: &[Vec2]) -> bool {
+    (0..corners.len())
+        .filter(|index| {
+            let (a, b) = (corners[*index], corners[(index + 1) % corners.len()]);
+            (a.y > point.y) != (b.y > point.y)
+                && point.x < a.x + (point.y - a.y) * (b.x - a.x) / (b.y - a.y)
+        })
+        .count()
+        % 2
+        == 1
+}
+
+/// Whether the holes are inside the outline without touching it or each other, so bridges can join them.
+fn holes_fit(outline: &[Vec2], holes: &[Vec<Vec2>]) -> bool {
+    // Polygons that don't touch are either apart, or one is inside the other with all its corners
+    holes.iter().enumerate().all(|(index, hole)| {
+        !touch(hole, outline)
+            && is_inside_polygon(hole[0], outline)
+            && holes[index + 1..].iter().all(|other| {
+                !touch(hole, other)
+                    && !is_inside_polygon(hole[0], other)
+                    && !is_inside_polygon(other[0], hole)
+            })
+    })
+}
+
+fn oriented(corners: &[Vec2], counter_clockwise: bool) -> Vec<Vec2> {
+    let mut corners = corners.to_vec();
+    if (signed_area(&corners) > 0.0) != counter_clockwise {
+        corners.reverse();
+    }
+    corners
+}
+
+///
+/// Splits a simple polygon with holes into counter clockwise triangles by ear clipping.
+///
+/// The outline and holes may go around either way. Holes must be inside the outline and not touch it or each other.
+/// Returns `None` if the polygon can't be split, which happens when it crosses itself, has fewer than 3 corners,
+/// or has holes that aren't inside the outline or touch it or each other.
+///
+pub fn triangulate(outline: &[Vec2], holes: &[Vec<Vec2>]) -> Option<Vec<Triangle>> {
+    if std::iter::once(outline)
+        .chain(holes.iter().map(Vec::as_slice))
+        .any(|corners| corners.len() < 3 || crosses_itself(corners))
+        || !holes_fit(outline, holes)
+    {
+        return None;
+    }
+
+    let mut polygon = oriented(outline, true);
+    let mut holes: Vec<Vec<Vec2>> = holes.iter().map(|hole| oriented(hole, false)).collect();
+    // Bridging the rightmost holes first means bridges never cross the holes bridged after them
+    let max_x = |corners: &Vec<Vec2>| {
+        corners
+            .iter()
+            .map(|corner| corner.x)
+            .fold(f32::MIN, f32::max)
+    };
+    holes.sort_by(|hole_1, hole_2| max_x(hole_2).total_cmp(&max_x(hole_1)));
+    for hole in holes.iter() {
+        polygon = bridge_hole(&polygon, hole)?;
+    }
+
+    clip_ears(polygon)
+}
+
+///
+/// Joins the hole into the polygon through a bridge from the hole's rightmost corner to a corner of the polygon
+/// it can see, going out and back along the bridge. That leaves a single polygon without holes.
+///
+fn bridge_hole(polygon: &[Vec2], hole: &[Vec2]) -> Option<Vec<Vec2>> {
+    let (hole_index, hole_corner) =
+        hole.iter()
+            .copied()
+            .enumerate()
+            .reduce(|rightmost, corner| {
+                if corner.1.x > rightmost.1.x {
+                    corner
+                } else {
+                    rightmost
+                }
+            })?;
+
+    // Find the closest side hit by a ray going right from the hole's corner, and the side's rightmost end
+    let mut closest: Option<(f32, usize)> = None;
+    for index in 0..polygon.len() {
+        let next_index = (index + 1) % polygon.len();
+        let (a, b) = (polygon[index], polygon[next_index]);
+        if a.y == b.y || (hole_corner.y - a.y) * (hole_corner.y - b.y) > 0.0 {
+            continue;
+        }
+        let x = a.x + (hole_corner.y - a.y) * (b.x - a.x) / (b.y - a.y);
+        if x >= hole_corner.x && closest.is_none_or(|(closest_x, _)| x < closest_x) {
+            closest = Some((x, if a.x > b.x { index } else { next_index }));
+        }
+    }
+    let (hit_x, mut bridge_index) = closest?;
+
+    // Corners poking into the triangle between the hole, the hit and the side's end would block the bridge,
+    // and the one closest in angle to the ray can always be seen
+    let hit = glm::vec2(hit_x, hole_corner.y);
+    let side_end = polygon[bridge_index];
+    if hit != side_end {
+        let mut best: Option<(f32, f32)> = None;
+        for index in 0..polygon.len() {
+            let corner = polygon[index];
+            let previous = polygon[(index + polygon.len() - 1) % polygon.len()];
+            let next = polygon[(index + 1) % polygon.len()];
+            if index == bridge_index
+                || turn(previous, corner, next) > 0.0
+                || !is_inside_triangle(corner, hole_corner, hit, side_end)
+            {
+                continue;
+            }
+            let offset = corner - hole_corner;
+            let angle = -offset.x / offset.norm();
+            let distance = offset.norm_squared();
+            if best.is_none_or(|best| (angle, distance) < best) {
+                best = Some((angle, distance));
+                bridge_index = index;
+            }
+        }
+    }
+
+    let mut bridged = Vec::with_capacity(polygon.len() + hole.len() + 2);
+    bridged.extend_from_slice(&polygon[..=bridge_index]);
+    bridged.extend_from_slice(&hole[hole_index..]);
+    bridged.extend_from_slice(&hole[..=hole_index]);
+    bridged.extend_from_slice(&polygon[bridge_index..]);
+    Some(bridged)
+}
+
+/// Repeatedly cuts off a corner whose triangle has no other corner in it, until only one triangle is left.
+fn clip_ears(mut polygon: Vec<Vec2>) -> Option<Vec<Triangle>> {
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    while polygon.len() >= 3 {
+        let corners = polygon.len();
+        let corner_at = |index: usize| {
+            (
+                polygon[(index + corners - 1) % corners],
+                polygon[index],
+                polygon[(index + 1) % corners],
+            )
+        };
+
+        // Corners in the middle of a straight side don't add anything
+        if let Some(straight) = (0..corners).find(|index| {
+            let (a, b, c) = corner_at(*index);
+            turn(a, b, c).abs() < MIN_TURN && (b - a).dot(&(c - b)) >= 0.0
+        }) {
+            polygon.remove(straight);
+            continue;
+        }
+        if corners == 3 {
+            let (a, b, c) = corner_at(1);
+            triangles.push(Triangle::new(a, b, c));
+            break;
+        }
+
+        let ear = (0..corners).find(|index| {
+            let (a, b, c) = corner_at(*index);
+            turn(a, b, c) > 0.0
+                && !polygon.iter().any(|corner| {
+                    *corner != a
+                        && *corner != b
+                        && *corner != c
+                        && is_inside_triangle(*corner, a, b, c)
+                })
+        })?;
+        let (a, b, c) = corner_at(ear);
+        triangles.push(Triangle::new(a, b, c));
+        polygon.remove(ear);
+    }
+    Some(triangles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::triangle::TriangleCorner;
+    use super::*;
+
+    fn corners(points: &[(f32, f32)]) -> Vec<Vec2> {
+        points.iter().map(|&(x, y)| glm::vec2(x, y)).collect()
+    }
+
+    fn corners_of(triangle: &Triangle) -> [Vec2; 3] {
+        [
+            triangle.get_corner(TriangleCorner::A),
+            triangle.get_corner(TriangleCorner::B),
+            triangle.get_corner(TriangleCorner::C),
+        ]
+    }
+
+    /// Checks that the triangles are counter clockwise, cover the expected area and leave the points uncovered.
+    fn assert_covers(triangles: &[Triangle], area: f32, uncovered: &[Vec2]) {
+        let mut covered_area = 0.0;
+        for triangle in triangles {
+            let [a, b, c] = corners_of(triangle);
+            let triangle_area = signed_area(&[a, b, c]) / 2.0;
+            assert!(triangle_area > 0.0);
+            covered_area += triangle_area;
+            for point in uncovered {
+                assert!(
+                    !is_inside_triangle(*point, a, b, c),
+                    "{:?} is covered",
+                    point
+                );
+            }
+        }
+        assert!(
+            (covered_area - area).abs() < 0.001,
+            "{} isn't {}",
+            covered_area,
+            area
+        );
+    }
+
+    #[test]
+    fn outlines_with_holes_are_split_around_the_holes() {
+        let outline = corners(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        // The holes go around different ways, and the first one is bridged last
+        let left_hole = corners(&[(1.0, 1.0), (3.0, 1.0), (3.0, 3.0), (1.0, 3.0)]);
+        let right_hole = corners(&[(6.0, 5.0), (6.0, 8.0), (8.0, 8.0), (8.0, 5.0)]);
+        let triangles = triangulate(&outline, &[left_hole, right_hole]).unwrap();
+        // Each hole adds its corners and the two ends of its bridge
+        assert_eq!(triangles.len(), 4 + 2 * (4 + 2) - 2);
+        assert_covers(
+            &triangles,
+            100.0 - 4.0 - 6.0,
+            &[glm::vec2(2.0, 2.0), glm::vec2(7.0, 6.5)],
+        );
+    }
+
+    #[test]
+    fn concave_outlines_are_split() {
+        // An L shape, whose inner corner can't be an ear
+        let outline = corners(&[
+            (0.0, 0.0),
+            (4.0, 0.0),
+            (4.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 4.0),
+            (0.0, 4.0),
+        ]);
+        let triangles = triangulate(&outline, &[]).unwrap();
+        assert_eq!(triangles.len(), 4);
+        assert_covers(&triangles, 7.0, &[glm::vec2(2.0, 2.0)]);
+    }
+
+    #[test]
+    fn bridges_go_to_corners_in_the_way_of_the_ray() {
+        // The top right corner of the outline is dented inwards, in between the hole and the side the ray hits
+        let outline = corners(&[
+            (0.0, 0.0),
+            (10.0, 0.0),
+            (10.0, 10.0),
+            (8.0, 7.5),
+            (0.0, 10.0),
+        ]);
+        let hole = corners(&[(2.0, 4.0), (2.0, 6.0), (4.0, 6.0), (4.0, 4.0)]);
+        let bridged = bridge_hole(&outline, &hole).unwrap();
+        assert_eq!(
+            bridged,
+            corners(&[
+                (0.0, 0.0),
+                (10.0, 0.0),
+                (10.0, 10.0),
+                (8.0, 7.5),
+                (4.0, 6.0),
+                (4.0, 4.0),
+                (2.0, 4.0),
+                (2.0, 6.0),
+                (4.0, 6.0),
+                (8.0, 7.5),
+                (0.0, 10.0),
+            ])
+        );
+
+        let triangles = triangulate(&outline, &[hole]).unwrap();
+        assert_covers(
+            &triangles,
+            signed_area(&outline) / 2.0 - 4.0,
+            &[glm::vec2(3.0, 5.0), glm::vec2(8.0, 9.0)],
+        );
+    }
+
+    #[test]
+    fn polygons_crossing_themselves_are_rejected() {
+        let bow_tie = corners(&[(0.0, 0.0), (2.0, 2.0), (2.0, 0.0), (0.0, 2.0)]);
+        assert!(triangulate(&bow_tie, &[]).is_none());
+        let line = corners(&[(0.0, 0.0), (1.0, 0.0)]);
+        assert!(triangulate(&line, &[]).is_none());
+    }
+
+    #[test]
+    fn holes_outside_the_outline_or_touching_are_rejected() {
+        let outline = corners(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        let square = |x: f32, y: f32, size: f32| {
+            corners(&[(x, y), (x + size, y), (x + size, y + size), (x, y + size)])
+        };
+        let rejected = |holes: &[Vec<Vec2>]| triangulate(&outline, holes).is_none();
+        assert!(!rejected(&[square(1.0, 1.0, 2.0), square(5.0, 5.0, 2.0)]));
+        assert!(rejected(&[square(20.0, 1.0, 2.0)]));
+        // Crossing and touching the outline
+        assert!(rejected(&[square(9.0, 1.0, 2.0)]));
+        assert!(rejected(&[square(8.0, 1.0, 2.0)]));
+        // Touching, overlapping and inside each other
+        assert!(rejected(&[square(1.0, 1.0, 2.0), square(3.0, 1.0, 2.0)]));
+        assert!(rejected(&[square(1.0, 1.0, 2.0), square(2.0, 2.0, 2.0)]));
+        assert!(rejected(&[square(1.0, 1.0, 6.0), square(2.0, 2.0, 2.0)]));
+        assert!(rejected(&[square(2.0, 2.0, 2.0), square(1.0, 1.0, 6.0)]));
+    }
+}
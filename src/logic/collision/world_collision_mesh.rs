@@ -1,20 +1,46 @@
 use glm::Vec2;
 
-use super::{Triangle, Aabb};
+use strum::IntoEnumIterator;
+
+use super::{triangle::TriangleCorner, Aabb, Bvh, Ray, RayHit, Triangle};
 
 pub struct WorldCollisionMesh {
-    triangles : Vec<Triangle>
+    triangles: Vec<Triangle>,
+    /// Hierarchy over the surrounding AABBs of the triangles, so only those near a collider are tested.
+    bvh: Bvh,
 }
 
 impl WorldCollisionMesh {
-    pub fn new(triangles : Vec<Triangle>) -> Self {
-        Self { triangles }
+    pub fn new(triangles: Vec<Triangle>) -> Self {
+        let aabbs: Vec<Aabb> = triangles
+            .iter()
+            .map(|triangle| *triangle.surrounding_aabb())
+            .collect();
+        Self {
+            bvh: Bvh::new(&aabbs),
+            triangles,
+        }
+    }
+
+    /// Returns the AABB around all the triangles, or `None` if there are none.
+    pub fn surrounding_aabb(&self) -> Option<Aabb> {
+        let aabbs: Vec<Aabb> = self
+            .triangles
+            .iter()
+            .map(|triangle| *triangle.surrounding_aabb())
+            .collect();
+        (!aabbs.is_empty()).then(|| Aabb::surrounding_aabb(&aabbs))
     }
 
-    pub fn find_collision(&self, aabb : &Aabb ) -> Option<Vec2> {
+    ///
+    /// Returns the longest of the vectors that push the AABB out of the triangles it collides with,
+    /// or the first one if several are equally long.
+    ///
+    pub fn find_collision(&self, aabb: &Aabb) -> Option<Vec2> {
         let mut intersection_vector = None;
         let mut intersection_dist = 0.0;
-        for triangle in self.triangles.iter() {
+        for index in self.bvh.find_colliding(aabb) {
+            let triangle = &self.triangles[index];
             if triangle.is_colliding(aabb) {
                 let test_intersection_vector = triangle.closest_intersection_vector(aabb);
                 if test_intersection_vector.magnitude_squared() > intersection_dist {
@@ -25,4 +51,56 @@ impl WorldCollisionMesh {
         }
         intersection_vector
     }
-}
\ No newline at end of file
+
+    ///
+    /// Whether the AABB overlaps any of the triangles at all. Unlike `find_collision`, which looks for crossing sides,
+    /// this also finds AABBs wholly inside a triangle and triangles wholly inside the AABB.
+    ///
+    pub fn is_overlapping(&self, aabb: &Aabb) -> bool {
+        let center = glm::vec2(
+            (aabb.min_x + aabb.max_x) / 2.0,
+            (aabb.min_y + aabb.max_y) / 2.0,
+        );
+        self.bvh.find_colliding(aabb).into_iter().any(|index| {
+            let triangle = &self.triangles[index];
+            triangle.is_colliding(aabb)
+                || triangle.is_point_inside(&center)
+                || TriangleCorner::iter().any(|corner| aabb.is_inside(triangle.get_corner(corner)))
+        })
+    }
+
+    ///
+    /// Returns when the AABB, moving by the displacement, first touches any of the triangles,
+    /// as a fraction of the displacement.
+    ///
+    pub fn time_of_impact(&self, aabb: &Aabb, displacement: Vec2) -> Option<f32> {
+        let swept_aabb = Aabb::surrounding_aabb(&[*aabb, *aabb + displacement]);
+        self.bvh
+            .find_colliding(&swept_aabb)
+            .into_iter()
+            .filter_map(|index| self.triangles[index].time_of_impact(aabb, displacement))
+            .reduce(f32::min)
+    }
+
+    /// Returns where the ray first hits a triangle, and the index of the triangle it hits.
+    pub fn cast_ray(&self, ray: &Ray) -> Option<(usize, RayHit)> {
+        let (start, end) = (ray.start_point(), ray.point_at(1.0));
+        let ray_aabb = Aabb {
+            min_x: start.x.min(end.x),
+            min_y: start.y.min(end.y),
+            max_x: start.x.max(end.x),
+            max_y: start.y.max(end.y),
+        };
+        self.bvh
+            .find_colliding(&ray_aabb)
+            .into_iter()
+            .filter_map(|index| self.triangles[index].cast_ray(ray).map(|hit| (index, hit)))
+            .reduce(|hit_1, hit_2| {
+                if hit_2.1.time < hit_1.1.time {
+                    hit_2
+                } else {
+                    hit_1
+                }
+            })
+    }
+}
@@ -0,0 +1,72 @@
+use glm::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::{Aabb, Bvh, Triangle};
+
+/// What the ground in an area of the world is made of, which changes how entities slow down on it.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SurfaceMaterial {
+    GROUND,
+    /// Entities keep sliding for a long time.
+    ICE,
+    /// Entities are slowed down a lot, and come to a stop quickly.
+    MUD,
+    /// Entities swim, with much more drag but nothing to rub against.
+    WATER,
+}
+
+impl SurfaceMaterial {
+    /// How much the material scales the linear drag and the ground friction of entities on it.
+    pub fn friction_scales(self) -> (f32, f32) {
+        match self {
+            SurfaceMaterial::GROUND => (1.0, 1.0),
+            SurfaceMaterial::ICE => (0.2, 0.05),
+            SurfaceMaterial::MUD => (2.5, 3.0),
+            SurfaceMaterial::WATER => (4.0, 0.0),
+        }
+    }
+}
+
+///
+/// Areas of the world made of triangles, each with the material of its ground.
+/// Unlike world collision triangles, entities walk over them freely.
+///
+pub struct WorldSurfaces {
+    triangles: Vec<(Triangle, SurfaceMaterial)>,
+    /// Hierarchy over the surrounding AABBs of the triangles, so only those near a point are tested.
+    bvh: Bvh,
+}
+
+impl WorldSurfaces {
+    pub fn new(triangles: Vec<(Triangle, SurfaceMaterial)>) -> Self {
+        let aabbs: Vec<Aabb> = triangles
+            .iter()
+            .map(|(triangle, _)| *triangle.surrounding_aabb())
+            .collect();
+        Self {
+            bvh: Bvh::new(&aabbs),
+            triangles,
+        }
+    }
+
+    ///
+    /// Returns the material of the ground at the point, which is plain ground outside of all the triangles.
+    /// Where triangles overlap, the one given last wins.
+    ///
+    pub fn material_at(&self, point: Vec2) -> SurfaceMaterial {
+        let point_aabb = Aabb {
+            min_x: point.x,
+            min_y: point.y,
+            max_x: point.x,
+            max_y: point.y,
+        };
+        self.bvh
+            .find_colliding(&point_aabb)
+            .into_iter()
+            .rev()
+            .map(|index| &self.triangles[index])
+            .find(|(triangle, _)| triangle.is_point_inside(&point))
+            .map_or(SurfaceMaterial::GROUND, |(_, material)| *material)
+    }
+}
@@ -1,12 +1,15 @@
+use std::collections::BTreeMap;
+
 use glm::Vec2;
+use serde::{Deserialize, Serialize};
 
-use super::collision::{CollisionMeshIdentifier, CollisionMeshManager};
+use super::collision::{CollisionLayer, CollisionLayers, CollisionMeshIdentifier};
 
 //
 // All Component structs
 //
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Position {
     pub x: f32,
     pub y: f32,
@@ -24,7 +27,7 @@ impl From<Position> for Vec2 {
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Velocity {
     pub dx: f32,
     pub dy: f32,
@@ -45,51 +48,460 @@ impl From<Velocity> for Vec2 {
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Asset {
     pub name: String,
+    #[serde(default)]
     pub animation: usize,
+    #[serde(default)]
     pub animation_start_time: f32,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct TimedLife {
     pub seconds_left: f32,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
-pub struct Friction {}
+///
+/// Slows the entity down over time, more or less depending on the material of the ground it is on.
+/// Linear drag takes away a fraction of the speed, so fast entities slow down the most,
+/// while ground friction takes away a fixed amount of speed, bringing slow entities to a full stop.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Friction {
+    /// Every second, the speed is divided by e to the power of this.
+    pub linear_drag: f32,
+    /// Speed taken away every second, in pixels per second.
+    pub ground_friction: f32,
+}
+
+impl Default for Friction {
+    /// Keeps a tenth of the speed after a second.
+    fn default() -> Self {
+        Self {
+            linear_drag: std::f32::consts::LN_10,
+            ground_friction: 0.0,
+        }
+    }
+}
+
+///
+/// Moves the entity by sweeping its collider along its velocity, so it can't pass through thin colliders
+/// or world collision triangles between ticks. Meant for fast movers, like arrows.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ContinuousCollision {}
+
+///
+/// Makes the entity's collider only detect what it overlaps, without pushing or being pushed.
+/// Its contacts still begin and end in `CollisionEvents`, which is how it notices things entering and leaving.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Sensor {}
 
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub struct Collider {
     pub collision_mesh: CollisionMeshIdentifier,
     pub size: f32,
+    /// The layers the collider is on.
+    pub layers: CollisionLayers,
+    /// The layers of the colliders it collides with.
+    pub collides_with: CollisionLayers,
+}
+
+impl Collider {
+    /// Colliders collide only if each of them collides with a layer the other is on.
+    pub fn can_collide(&self, collider: &Collider) -> bool {
+        self.collides_with.intersects(collider.layers)
+            && collider.collides_with.intersects(self.layers)
+    }
+
+    pub fn collides_with_world(&self) -> bool {
+        self.collides_with.contains(CollisionLayer::WORLD)
+    }
+}
+
+///
+/// How the entity responds when it is pushed apart from what it overlaps.
+/// Moving entities without one act like ones with a mass of 1 that neither bounce nor slow down sliding.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct RigidBody {
+    /// Heavier entities are pushed less far by lighter ones. Must be above 0.
+    pub mass: f32,
+    /// Fraction of the speed towards what it hits that it bounces back with, from 0 to 1.
+    pub restitution: f32,
+    /// How much sliding along what it touches is slowed down, relative to how hard it is pressed against it.
+    pub friction: f32,
+}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self {
+            mass: 1.0,
+            restitution: 0.0,
+            friction: 0.0,
+        }
+    }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Team {
     PLAYER,
     ENEMY,
 }
 
-#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct Status {
-    pub collides_with_own_team: bool,
     pub team: Team,
 }
 
 impl Default for Status {
     fn default() -> Self {
-        Self {
-            collides_with_own_team: false,
-            team: Team::ENEMY,
-        }
+        Self { team: Team::ENEMY }
     }
 }
 
-#[derive(Debug, Copy, Clone, PartialEq)]
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
 pub struct AiRandomWalk {
     pub speed: f32,
     pub centering_speed : f32,
     pub center : Vec2,
 }
+
+///
+/// How much an entity can be hurt before it dies.
+/// Entities lose hit points when they touch an entity with `Damage` from the opposing team.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Health {
+    pub hit_points: f32,
+    /// Healing stops here. Without it, there is no limit.
+    #[serde(default)]
+    pub max_hit_points: Option<f32>,
+    /// How long the entity can't be hurt again after it was hit.
+    pub invulnerability_seconds: f32,
+    #[serde(default)]
+    pub invulnerable_seconds_left: f32,
+    /// Played before the entity is removed when it dies. Without one, the entity is removed right away.
+    #[serde(default)]
+    pub death_animation: Option<DeathAnimation>,
+}
+
+impl Health {
+    pub fn is_invulnerable(&self) -> bool {
+        self.invulnerable_seconds_left > 0.0
+    }
+
+    pub fn heal(&mut self, hit_points: f32) {
+        self.hit_points += hit_points;
+        if let Some(max_hit_points) = self.max_hit_points {
+            self.hit_points = self.hit_points.min(max_hit_points);
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct DeathAnimation {
+    /// Index of the animation in the entity's asset.
+    pub animation: usize,
+    pub seconds: f32,
+}
+
+/// Heals the first entity of the player's team with `Health` to touch it, and is removed once collected.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Pickup {
+    pub heal: f32,
+}
+
+/// Hit points taken from entities of the opposing team that this entity touches.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Damage {
+    pub amount: f32,
+    /// Projectiles are used up by the first entity they hurt, so they can't hit it again.
+    #[serde(default)]
+    pub removed_on_hit: bool,
+}
+
+/// Heads for the player while they are in sight.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AiChase {
+    pub speed: f32,
+    pub sight_range: f32,
+}
+
+/// Moves towards or away from the player to stay at the given distance while they are in sight.
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AiKeepDistance {
+    pub speed: f32,
+    pub distance: f32,
+    pub sight_range: f32,
+}
+
+/// Walks from one waypoint to the next, starting over after the last one.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AiPatrol {
+    pub speed: f32,
+    pub waypoints: Vec<Vec2>,
+    #[serde(default)]
+    pub next_waypoint: usize,
+}
+
+///
+/// Walks to a destination around obstacles, along a path found on the `NavigationGrid`.
+/// Other AI components pick the destination and speed, and the path is found again now and then as things move.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AiFollowPath {
+    /// How far the entity reaches out from its position, so it only walks where it fits.
+    pub radius: f32,
+    #[serde(default)]
+    pub speed: f32,
+    #[serde(default)]
+    pub destination: Option<Vec2>,
+    #[serde(default)]
+    pub path: Vec<Vec2>,
+    #[serde(default)]
+    pub next_waypoint: usize,
+    #[serde(default)]
+    pub repath_seconds_left: f32,
+}
+
+impl AiFollowPath {
+    /// Walks to the destination at the speed, or stops if there is none.
+    pub fn go_to(&mut self, destination: Option<Vec2>, speed: f32) {
+        if self.destination.is_none() {
+            // Find a path right away instead of waiting for the next time
+            self.repath_seconds_left = 0.0;
+        }
+        self.destination = destination;
+        self.speed = speed;
+    }
+}
+
+/// What a seeking or arriving entity is drawn towards.
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub enum SteeringTarget {
+    PLAYER,
+    /// The entity's `AiSteering::home`.
+    HOME,
+    /// The nearest entity with a `Lure`.
+    LURE,
+}
+
+/// Heads for the target at full speed while it is in range.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct SeekBehaviour {
+    pub target: SteeringTarget,
+    pub weight: f32,
+    /// Without one, the target is sought from any distance.
+    #[serde(default)]
+    pub range: Option<f32>,
+}
+
+/// Heads for the target while it is in range, slowing down to stop on it.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct ArriveBehaviour {
+    pub target: SteeringTarget,
+    pub weight: f32,
+    /// Without one, the target is sought from any distance.
+    #[serde(default)]
+    pub range: Option<f32>,
+    /// How close to the target the entity starts slowing down.
+    pub slowing_distance: f32,
+}
+
+///
+/// Heads for a point on a circle in front of the entity, which drifts around the circle at random,
+/// so the entity meanders instead of jittering.
+///
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct WanderBehaviour {
+    pub weight: f32,
+    pub radius: f32,
+    /// How far in front of the entity the circle is.
+    pub distance: f32,
+    /// How fast the point can drift around the circle, in radians per second.
+    pub jitter: f32,
+    #[serde(default)]
+    pub angle: f32,
+}
+
+/// Reacts to the other steering entities within the radius, for separation, alignment and cohesion.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct FlockBehaviour {
+    pub weight: f32,
+    pub radius: f32,
+}
+
+///
+/// Moves the entity by weighing and adding up steering behaviours.
+/// Each behaviour asks for a change to the entity's velocity, which flocking ones base on the other steering entities nearby.
+/// The entity accelerates towards the combined velocity, reaching full acceleration when it is off by `max_speed`.
+///
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AiSteering {
+    /// Must be above 0.
+    pub max_speed: f32,
+    pub max_acceleration: f32,
+    /// Where the entity belongs. Without one, it is where the entity first steers from.
+    #[serde(default)]
+    pub home: Option<Vec2>,
+    #[serde(default)]
+    pub seek: Vec<SeekBehaviour>,
+    #[serde(default)]
+    pub arrive: Vec<ArriveBehaviour>,
+    #[serde(default)]
+    pub wander: Option<WanderBehaviour>,
+    /// Keeps away from neighbours.
+    #[serde(default)]
+    pub separation: Option<FlockBehaviour>,
+    /// Matches the neighbours' average velocity.
+    #[serde(default)]
+    pub alignment: Option<FlockBehaviour>,
+    /// Heads for the center of the neighbours.
+    #[serde(default)]
+    pub cohesion: Option<FlockBehaviour>,
+}
+
+/// Something about the entity and its surroundings that a state machine checks before changing state.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "condition")]
+pub enum AiCondition {
+    PlayerCloserThan {
+        distance: f32,
+    },
+    PlayerFurtherThan {
+        distance: f32,
+    },
+    /// Never holds for entities without `Health`.
+    HitPointsBelow {
+        hit_points: f32,
+    },
+    SecondsInStateOver {
+        seconds: f32,
+    },
+    /// Missing blackboard values count as 0.
+    BlackboardAbove {
+        key: String,
+        value: f32,
+    },
+    BlackboardBelow {
+        key: String,
+        value: f32,
+    },
+}
+
+impl AiCondition {
+    pub fn holds(
+        &self,
+        state_machine: &AiStateMachine,
+        player_distance: f32,
+        health: Option<&Health>,
+    ) -> bool {
+        let blackboard = |key: &str| state_machine.blackboard.get(key).copied().unwrap_or(0.0);
+        match self {
+            AiCondition::PlayerCloserThan { distance } => player_distance < *distance,
+            AiCondition::PlayerFurtherThan { distance } => player_distance > *distance,
+            AiCondition::HitPointsBelow { hit_points } => {
+                health.is_some_and(|health| health.hit_points < *hit_points)
+            }
+            AiCondition::SecondsInStateOver { seconds } => {
+                state_machine.seconds_in_state > *seconds
+            }
+            AiCondition::BlackboardAbove { key, value } => blackboard(key) > *value,
+            AiCondition::BlackboardBelow { key, value } => blackboard(key) < *value,
+        }
+    }
+}
+
+/// A change to another state, taken once all of its conditions hold. Without conditions, it is taken right away.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AiStateTransition {
+    pub to: String,
+    #[serde(default)]
+    pub when: Vec<AiCondition>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AiState {
+    ///
+    /// Components the entity has while in the state, described as in prefabs.
+    /// They are added on entering the state and removed on leaving it, even if the entity had them before.
+    ///
+    #[serde(default)]
+    pub components: toml::value::Table,
+    /// Blackboard values set on entering the state.
+    #[serde(default)]
+    pub set: BTreeMap<String, f32>,
+    /// Amounts added to blackboard values on entering the state, like counting how often it was entered.
+    #[serde(default)]
+    pub add: BTreeMap<String, f32>,
+    /// Checked in order, and the first one whose conditions hold is taken.
+    #[serde(default)]
+    pub transitions: Vec<AiStateTransition>,
+}
+
+///
+/// Composes behaviour out of other components, by switching between named states that each give the entity
+/// some components, like `ai_chase` or `ai_steering`.
+/// The blackboard holds values of the entity's own that states set and conditions check.
+///
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct AiStateMachine {
+    /// The state the entity enters when it is spawned.
+    pub initial_state: String,
+    pub states: BTreeMap<String, AiState>,
+    /// The state the entity is in, if it has entered one yet.
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub seconds_in_state: f32,
+    #[serde(default)]
+    pub blackboard: BTreeMap<String, f32>,
+}
+
+impl AiStateMachine {
+    pub fn current_state(&self) -> Option<&AiState> {
+        self.state.as_ref().and_then(|state| self.states.get(state))
+    }
+}
+
+/// Draws entities with a `SteeringTarget::LURE` behaviour towards it, like a light draws moths.
+#[derive(Copy, Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub struct Lure {}
+
+/// Shoots projectiles spawned from the named prefab at the player while they are in range.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AiShoot {
+    pub projectile: String,
+    pub projectile_speed: f32,
+    pub range: f32,
+    pub cooldown_seconds: f32,
+    #[serde(default)]
+    pub cooldown_seconds_left: f32,
+}
+
+///
+/// Runs away from the player once its `Health` drops to the given hit points.
+/// A fleeing entity does nothing else its AI components would have it do.
+///
+#[derive(Debug, Copy, Clone, PartialEq, Deserialize, Serialize)]
+pub struct AiFlee {
+    pub speed: f32,
+    pub below_hit_points: f32,
+}
+
+impl AiFlee {
+    pub fn is_fleeing(flee: Option<&AiFlee>, health: Option<&Health>) -> bool {
+        match (flee, health) {
+            (Some(flee), Some(health)) => health.hit_points <= flee.below_hit_points,
+            _ => false,
+        }
+    }
+}
@@ -90,12 +90,12 @@ fn jump_default() -> Control {
 
 fn save_default() -> Control {
     Control::Keyboard {
-        key_code: VirtualKeyCode::S,
+        key_code: VirtualKeyCode::F5,
     }
 }
 fn load_default() -> Control {
     Control::Keyboard {
-        key_code: VirtualKeyCode::L,
+        key_code: VirtualKeyCode::F9,
     }
 }
 fn player_interact_1_default() -> Control {
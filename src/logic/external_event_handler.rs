@@ -1,4 +1,5 @@
 use super::controls::{Control, ControlConfig};
+use super::session_event::SessionEvent;
 use super::state_input_event::StateInputEvent;
 use super::Direction;
 use crate::graphics::ExternalEvent;
@@ -14,6 +15,8 @@ pub struct ExternalEventHandler {
     button_state: HashMap<MouseButton, bool>,
     /// The state events generated this tick.
     tick_state_events: Vec<StateInputEvent>,
+    /// The session events generated this tick.
+    tick_session_events: Vec<SessionEvent>,
     /// Configuration for controls.
     control_config: ControlConfig,
 }
@@ -24,6 +27,7 @@ impl ExternalEventHandler {
             key_state: HashMap::new(),
             button_state: HashMap::new(),
             tick_state_events: Vec::new(),
+            tick_session_events: Vec::new(),
             control_config,
         }
     }
@@ -95,6 +99,12 @@ impl ExternalEventHandler {
         if control == self.control_config.jump {
             self.tick_state_events.push(StateInputEvent::Jump)
         }
+        if control == self.control_config.save {
+            self.tick_session_events.push(SessionEvent::Save)
+        }
+        if control == self.control_config.load {
+            self.tick_session_events.push(SessionEvent::Load)
+        }
     }
 
     fn handle_control_release(&mut self, control: Control) {
@@ -116,9 +126,14 @@ impl ExternalEventHandler {
         }
     }
 
+    /// Returns and clears the current session event buffer.
+    pub fn tick_session_events(&mut self) -> Vec<SessionEvent> {
+        std::mem::take(&mut self.tick_session_events)
+    }
+
     /// Returns and clears the current event buffer.
     pub fn tick_events(&mut self) -> Vec<StateInputEvent> {
-        let mut state_result = std::mem::replace(&mut self.tick_state_events, Vec::new());
+        let mut state_result = std::mem::take(&mut self.tick_state_events);
         let mut move_vector = Vec2::new(0., 0.);
         if self.control_state(self.control_config.move_forward) {
             move_vector += Vec2::new(0., 1.);
@@ -0,0 +1,1454 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use glm::Vec2;
+use legion::{query::component, *};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+
+use super::{
+    camera::Camera,
+    collision::{
+        Aabb, CollisionMesh, CollisionMeshIdentifier, CollisionMeshManager, Ray, RayHit, Shape,
+        SpatialHash, WorldCollisionMesh,
+    },
+    level::{Level, LevelError},
+    navigation::NavigationGrid,
+    prefab::{enter_ai_state, remove_behaviour, Prefabs},
+    save_game::{SaveError, SaveGame},
+    state_input_event::StateInputEvent,
+    *,
+};
+
+/// The prefabs that levels and gameplay spawn entities from.
+const PREFABS_PATH: &str = "assets/prefabs.toml";
+
+/// Size of the broadphase grid cells, a bit larger than most colliders.
+pub const BROADPHASE_CELL_SIZE: f32 = 128.0;
+
+/// Size of the navigation grid cells, small enough for paths to squeeze between most props.
+const NAVIGATION_CELL_SIZE: f32 = 32.0;
+
+/// How far the navigation grid reaches past the entities and world collision triangles of the level.
+const NAVIGATION_MARGIN: f32 = 512.0;
+
+/// How far past their time of impact continuously colliding entities move, so the collision pass finds them overlapping.
+const CONTINUOUS_COLLISION_PENETRATION: f32 = 1.0;
+
+///
+/// Builds the collision meshes that colliders refer to by name.
+/// All meshes fit in a square of size 1 around the entity, so `Collider::size` scales them the same way.
+///
+pub fn collision_meshes() -> (
+    CollisionMeshManager,
+    HashMap<String, CollisionMeshIdentifier>,
+) {
+    let mut collision_mesh_manager = CollisionMeshManager::new();
+    let mut collision_mesh_identifiers: HashMap<String, CollisionMeshIdentifier> = HashMap::new();
+
+    let octagon_corners = (0..8)
+        .map(|corner| {
+            let angle = (corner as f32 + 0.5) * std::f32::consts::TAU / 8.0;
+            glm::vec2(angle.cos(), angle.sin()) * 0.5
+        })
+        .collect();
+    for (name, shape) in [
+        (
+            "basic",
+            Shape::Aabb(Aabb {
+                min_x: -0.5,
+                min_y: -0.5,
+                max_x: 0.5,
+                max_y: 0.5,
+            }),
+        ),
+        (
+            "circle",
+            Shape::Circle {
+                center: glm::vec2(0.0, 0.0),
+                radius: 0.5,
+            },
+        ),
+        (
+            "capsule",
+            Shape::Capsule {
+                start: glm::vec2(0.0, -0.25),
+                end: glm::vec2(0.0, 0.25),
+                radius: 0.25,
+            },
+        ),
+        ("octagon", Shape::Polygon(octagon_corners)),
+    ] {
+        let identifier = collision_mesh_manager.add_collision_mesh(CollisionMesh::new(shape));
+        collision_mesh_identifiers.insert(name.into(), identifier);
+    }
+
+    (collision_mesh_manager, collision_mesh_identifiers)
+}
+
+pub fn setup_schedule() -> Schedule {
+    Schedule::builder()
+        .add_system(update_positions_system())
+        .add_system(update_velocities_system())
+        .add_system(update_lives_system())
+        .add_system(state_machine_ai_system())
+        .add_system(random_walk_ai_system())
+        .add_system(steering_ai_system())
+        .add_system(chase_ai_system())
+        .add_system(follow_path_system())
+        .add_system(keep_distance_ai_system())
+        .add_system(patrol_ai_system())
+        .add_system(flee_ai_system())
+        .add_system(shoot_ai_system())
+        .add_system(update_invulnerability_system())
+        .build()
+}
+
+/// Systems reacting to the collisions found in a tick, before they are resolved.
+pub fn setup_collision_schedule() -> Schedule {
+    Schedule::builder()
+        .add_system(apply_damage_system())
+        .add_system(collect_pickups_system())
+        .build()
+}
+
+pub fn setup_resources(seed: u64, tick_seconds: f32) -> Resources {
+    let mut resources = Resources::default();
+    resources.insert(Time {
+        elapsed_seconds: tick_seconds,
+    });
+    resources.insert(ChaCha12Rng::seed_from_u64(seed));
+    resources.insert(PlayerPosition {
+        position: Vec2::zeros(),
+    });
+    resources.insert(Shots::default());
+    resources.insert(StateChanges::default());
+    resources.insert(CollisionEvents::default());
+
+    resources
+}
+
+#[derive(Default, Clone, Serialize, Deserialize)]
+///
+/// TODO: BAD NAME
+/// All the stuff around the player, like if they're looking somewhere or if the camera's shaking or whatever.
+pub struct ExtraInfo {
+    pub shake: f32,
+    speed: f32,
+    charge: u32,
+}
+
+impl ExtraInfo {
+    pub fn new() -> Self {
+        Self {
+            shake: 0.0,
+            speed: 16.0,
+            ..Default::default()
+        }
+    }
+    pub fn update(&mut self) {
+        self.shake *= 0.98;
+    }
+}
+
+fn handle_timed_life(world: &mut World) {
+    let mut q = <(Entity, &TimedLife)>::query();
+    let removed_entities: Vec<Entity> = q
+        .iter(world)
+        .flat_map(|(entity, time)| {
+            if time.seconds_left <= 0.0 {
+                Some(*entity)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    for entity in removed_entities {
+        world.remove(entity);
+    }
+}
+
+///
+/// The position and velocity of the player.
+fn player_motion(world: &World, player: Entity) -> (Vec2, Vec2) {
+    let player_entry = world
+        .entry_ref(player)
+        .expect("The player has disappeared!");
+    (
+        Vec2::from(*player_entry.get_component::<Position>().unwrap()),
+        player_entry
+            .get_component::<Velocity>()
+            .map_or(Vec2::zeros(), |velocity| Vec2::from(*velocity)),
+    )
+}
+
+/// Removes entities other than the player that ran out of hit points.
+/// Entities with a death animation play it first, and can no longer collide, hurt, be hurt or act while doing so.
+///
+fn handle_deaths(world: &mut World, player: Entity, game_time: f32) {
+    let mut q = <(Entity, &Health)>::query();
+    let dead_entities: Vec<(Entity, Option<DeathAnimation>)> = q
+        .iter(world)
+        .filter(|(entity, health)| **entity != player && health.hit_points <= 0.0)
+        .map(|(entity, health)| (*entity, health.death_animation))
+        .collect();
+
+    for (entity, death_animation) in dead_entities {
+        match death_animation {
+            Some(death_animation) => {
+                let mut entry = world.entry(entity).unwrap();
+                entry.remove_component::<Health>();
+                entry.remove_component::<Damage>();
+                entry.remove_component::<Collider>();
+                remove_behaviour(&mut entry);
+                if let Ok(asset) = entry.get_component_mut::<Asset>() {
+                    asset.animation = death_animation.animation;
+                    asset.animation_start_time = game_time;
+                }
+                entry.add_component(TimedLife {
+                    seconds_left: death_animation.seconds,
+                });
+            }
+            None => {
+                world.remove(entity);
+            }
+        }
+    }
+}
+
+///
+/// Builds the navigation grid over the level, with the world collision triangles and the colliders that don't move
+/// as obstacles. Sensors are walked through, so they aren't obstacles.
+///
+fn build_navigation_grid(
+    world: &World,
+    collision_mesh_manager: &CollisionMeshManager,
+    world_collision_mesh: &WorldCollisionMesh,
+) -> NavigationGrid {
+    let obstacles: Vec<Aabb> = <(&Position, &Collider)>::query()
+        .filter(!component::<Velocity>() & !component::<Sensor>())
+        .iter(world)
+        .map(|(position, collider)| {
+            collision_mesh_manager
+                .get_collision_mesh(
+                    collider.collision_mesh,
+                    Vec2::from(*position),
+                    collider.size,
+                )
+                .aabb
+        })
+        .collect();
+
+    let mut aabbs: Vec<Aabb> = <&Position>::query()
+        .iter(world)
+        .map(|position| Aabb {
+            min_x: position.x,
+            min_y: position.y,
+            max_x: position.x,
+            max_y: position.y,
+        })
+        .chain(obstacles.iter().copied())
+        .collect();
+    aabbs.extend(world_collision_mesh.surrounding_aabb());
+    let bounds = Aabb::surrounding_aabb(&aabbs);
+    let bounds = Aabb {
+        min_x: bounds.min_x - NAVIGATION_MARGIN,
+        min_y: bounds.min_y - NAVIGATION_MARGIN,
+        max_x: bounds.max_x + NAVIGATION_MARGIN,
+        max_y: bounds.max_y + NAVIGATION_MARGIN,
+    };
+
+    NavigationGrid::new(&bounds, NAVIGATION_CELL_SIZE, |cell| {
+        world_collision_mesh.is_overlapping(cell)
+            || obstacles.iter().any(|obstacle| obstacle.is_colliding(cell))
+    })
+}
+
+///
+/// Moves entities with `ContinuousCollision` along their velocity,
+/// stopping them at the first collider or world collision triangle they would hit on the way.
+/// Sensors neither stop nor are stopped. They end up just overlapping whatever they hit, so the collision pass handles the hit like any other.
+///
+fn move_continuously(
+    world: &mut World,
+    collision_mesh_manager: &CollisionMeshManager,
+    world_collision_mesh: &WorldCollisionMesh,
+    elapsed_seconds: f32,
+) {
+    let colliders: Vec<(Entity, CollisionMesh, Collider)> =
+        <(Entity, &Position, &Collider)>::query()
+            .filter(!component::<Sensor>())
+            .iter(world)
+            .map(|(entity, position, collider)| {
+                (
+                    *entity,
+                    collision_mesh_manager.get_collision_mesh(
+                        collider.collision_mesh,
+                        Vec2::from(*position),
+                        collider.size,
+                    ),
+                    *collider,
+                )
+            })
+            .collect();
+
+    let mut movers = <(Entity, &mut Position, &Velocity, &ContinuousCollision)>::query();
+    for (entity, position, velocity, _) in movers.iter_mut(world) {
+        let displacement = Vec2::from(*velocity) * elapsed_seconds;
+        let time_of_impact = colliders
+            .iter()
+            .find(|(collider, _, _)| collider == entity)
+            .and_then(|(_, collision_mesh, collider)| {
+                // Like the collision pass, the world collision mesh is tested against the surrounding aabb
+                let world_time_of_impact = if collider.collides_with_world() {
+                    world_collision_mesh.time_of_impact(&collision_mesh.aabb, displacement)
+                } else {
+                    None
+                };
+                colliders
+                    .iter()
+                    .filter(|(other, _, other_collider)| {
+                        other != entity && collider.can_collide(other_collider)
+                    })
+                    .filter_map(|(_, other_collision_mesh, _)| {
+                        collision_mesh
+                            .shape
+                            .time_of_impact(displacement, &other_collision_mesh.shape)
+                    })
+                    .chain(world_time_of_impact)
+                    .reduce(f32::min)
+            });
+
+        let distance = displacement.norm();
+        let travelled = match time_of_impact {
+            Some(time_of_impact) => {
+                (distance * time_of_impact + CONTINUOUS_COLLISION_PENETRATION).min(distance)
+            }
+            None => distance,
+        };
+        if distance > 0.0 {
+            let moved = displacement * (travelled / distance);
+            position.x += moved.x;
+            position.y += moved.y;
+        }
+    }
+}
+
+///
+/// Returns the contacts between the colliders, and between them and the world collision mesh.
+/// Contacts are given as indices into the colliders, with `None` standing for the world collision mesh.
+///
+fn find_contacts(
+    colliders: &[(Entity, Collider, CollisionMesh)],
+    broadphase: &mut SpatialHash,
+    world_collision_mesh: &WorldCollisionMesh,
+) -> Vec<(usize, Option<usize>)> {
+    let aabbs: Vec<Aabb> = colliders.iter().map(|(_, _, mesh)| mesh.aabb).collect();
+
+    let mut contacts: Vec<(usize, Option<usize>)> = Vec::new();
+    for (index_1, index_2) in broadphase.colliding_pairs(&aabbs) {
+        let (_, collider_1, collision_mesh_1) = &colliders[index_1];
+        let (_, collider_2, collision_mesh_2) = &colliders[index_2];
+        // Filtering by layer first spares the narrowphase test for pairs that never collide
+        if collider_1.can_collide(collider_2) && collision_mesh_1.is_colliding(collision_mesh_2) {
+            contacts.push((index_1, Some(index_2)));
+        }
+    }
+    for (index, (_, collider, collision_mesh)) in colliders.iter().enumerate() {
+        if collider.collides_with_world()
+            && world_collision_mesh
+                .find_collision(&collision_mesh.aabb)
+                .is_some()
+        {
+            contacts.push((index, None));
+        }
+    }
+    contacts
+}
+
+/// Turns the contacts found between the colliders into contacts between entities, going both ways.
+fn contacts_both_ways(
+    colliders: &[(Entity, Collider, CollisionMesh)],
+    contacts: &[(usize, Option<usize>)],
+) -> Vec<Contact> {
+    contacts
+        .iter()
+        .flat_map(|(index_1, index_2)| {
+            let entity_1 = colliders[*index_1].0;
+            let entity_2 = index_2.map(|index_2| colliders[index_2].0);
+            let contact = Contact {
+                entity: entity_1,
+                other: entity_2,
+            };
+            let reverse = entity_2.map(|entity_2| Contact {
+                entity: entity_2,
+                other: Some(entity_1),
+            });
+            std::iter::once(contact).chain(reverse)
+        })
+        .collect()
+}
+
+/// Sensors only detect overlaps, so their contacts are dropped before anything gets pushed out of them.
+fn remove_sensor_contacts(
+    world: &World,
+    colliders: &[(Entity, Collider, CollisionMesh)],
+    contacts: &mut Vec<(usize, Option<usize>)>,
+) {
+    let sensors: HashSet<Entity> = <(Entity, &Collider, &Sensor)>::query()
+        .iter(world)
+        .map(|(entity, _, _)| *entity)
+        .collect();
+    contacts.retain(|(index_1, index_2)| {
+        !sensors.contains(&colliders[*index_1].0)
+            && index_2.is_none_or(|index_2| !sensors.contains(&colliders[index_2].0))
+    });
+}
+
+/// Below this, overlapping entities are considered already pushed apart.
+const MIN_PENETRATION: f32 = 0.0001;
+
+/// How an entity takes part in pushing contacts apart, derived from its `RigidBody`.
+struct Body {
+    /// Zero for entities that can't move, which is those without a `Velocity`.
+    inverse_mass: f32,
+    restitution: f32,
+    friction: f32,
+}
+
+///
+/// Pushes the colliders of the contacts apart, then makes them bounce off and slide along each other.
+///
+/// Each contact is pushed apart by splitting the overlap between both entities by their inverse mass,
+/// so lighter entities move further. Pushing one contact apart can push another one together,
+/// so every contact is tested and pushed apart again for the given number of iterations.
+/// Contacts are given as indices into the colliders, with `None` standing for the world collision mesh.
+///
+fn resolve_contacts(
+    world: &mut World,
+    colliders: &[(Entity, Collider, CollisionMesh)],
+    contacts: &[(usize, Option<usize>)],
+    world_collision_mesh: &WorldCollisionMesh,
+    solver_iterations: usize,
+) {
+    // Entities removed while reacting to the contacts don't move anymore
+    let bodies: Vec<(Body, Vec2)> = colliders
+        .iter()
+        .map(|(entity, _, _)| {
+            let entry = world.entry_ref(*entity).ok();
+            let rigid_body = entry
+                .as_ref()
+                .and_then(|entry| entry.get_component::<RigidBody>().ok().copied())
+                .unwrap_or_default();
+            let velocity = entry
+                .as_ref()
+                .and_then(|entry| entry.get_component::<Velocity>().ok().copied());
+            let body = Body {
+                inverse_mass: if velocity.is_some() {
+                    1.0 / rigid_body.mass
+                } else {
+                    0.0
+                },
+                restitution: rigid_body.restitution,
+                friction: rigid_body.friction,
+            };
+            (body, velocity.map_or_else(Vec2::zeros, Vec2::from))
+        })
+        .collect();
+    let inverse_mass =
+        |index: Option<usize>| index.map_or(0.0, |index| bodies[index].0.inverse_mass);
+
+    let mut corrections = vec![Vec2::zeros(); colliders.len()];
+    // The direction each contact pushed the first entity in, the first time it did
+    let mut normals: Vec<Option<Vec2>> = vec![None; contacts.len()];
+    for _ in 0..solver_iterations {
+        for (contact, (index_1, index_2)) in contacts.iter().enumerate() {
+            let total_inverse_mass = inverse_mass(Some(*index_1)) + inverse_mass(*index_2);
+            if total_inverse_mass == 0.0 {
+                continue;
+            }
+            let collision_mesh_1 = colliders[*index_1].2.transform(corrections[*index_1], 1.0);
+            let collision_vector = match index_2 {
+                Some(index_2) => collision_mesh_1.closest_intersection_vector(
+                    &colliders[*index_2].2.transform(corrections[*index_2], 1.0),
+                ),
+                None => world_collision_mesh
+                    .find_collision(&collision_mesh_1.aabb)
+                    .unwrap_or_else(Vec2::zeros),
+            };
+            if collision_vector.norm() < MIN_PENETRATION {
+                continue;
+            }
+
+            normals[contact].get_or_insert(collision_vector.normalize());
+            corrections[*index_1] +=
+                collision_vector * (inverse_mass(Some(*index_1)) / total_inverse_mass);
+            if let Some(index_2) = index_2 {
+                corrections[*index_2] -=
+                    collision_vector * (inverse_mass(Some(*index_2)) / total_inverse_mass);
+            }
+        }
+    }
+
+    let mut velocities: Vec<Vec2> = bodies.iter().map(|(_, velocity)| *velocity).collect();
+    for (contact, (index_1, index_2)) in contacts.iter().enumerate() {
+        let normal = match normals[contact] {
+            Some(normal) => normal,
+            None => continue,
+        };
+        let velocity_2 = index_2.map_or_else(Vec2::zeros, |index_2| velocities[index_2]);
+        let relative_velocity = velocities[*index_1] - velocity_2;
+        let normal_speed = relative_velocity.dot(&normal);
+        if normal_speed >= 0.0 {
+            // Already moving apart
+            continue;
+        }
+
+        // Bounce as much as the bouncier entity, and slide as if both surfaces rubbed against each other
+        let body_1 = &bodies[*index_1].0;
+        let (restitution, friction) = match index_2 {
+            Some(index_2) => {
+                let body_2 = &bodies[*index_2].0;
+                (
+                    body_1.restitution.max(body_2.restitution),
+                    (body_1.friction * body_2.friction).sqrt(),
+                )
+            }
+            None => (body_1.restitution, body_1.friction),
+        };
+        let total_inverse_mass = inverse_mass(Some(*index_1)) + inverse_mass(*index_2);
+        let normal_impulse = -(1.0 + restitution) * normal_speed / total_inverse_mass;
+        let tangent_velocity = relative_velocity - normal * normal_speed;
+        let tangent_speed = tangent_velocity.norm();
+        // Friction can stop the sliding, but never reverse it
+        let friction_impulse = if tangent_speed > 0.0 {
+            -tangent_velocity / tangent_speed
+                * (friction * normal_impulse).min(tangent_speed / total_inverse_mass)
+        } else {
+            Vec2::zeros()
+        };
+
+        let impulse = normal * normal_impulse + friction_impulse;
+        velocities[*index_1] += impulse * inverse_mass(Some(*index_1));
+        if let Some(index_2) = index_2 {
+            velocities[*index_2] -= impulse * inverse_mass(Some(*index_2));
+        }
+    }
+
+    for (index, (entity, _, _)) in colliders.iter().enumerate() {
+        if inverse_mass(Some(index)) == 0.0 {
+            continue;
+        }
+        let mut entry = world.entry(*entity).unwrap();
+        let position = entry.get_component_mut::<Position>().unwrap();
+        position.x += corrections[index].x;
+        position.y += corrections[index].y;
+        *entry.get_component_mut::<Velocity>().unwrap() = Velocity::from(velocities[index]);
+    }
+}
+
+///
+/// Pushes a projectile from the named prefab into the world, flying from the position with the velocity.
+///
+fn spawn_projectile(
+    world: &mut World,
+    prefabs: &Prefabs,
+    collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+    shot: Shot,
+    game_time: f32,
+) {
+    let projectile = prefabs
+        .spawn_prefab(world, &shot.projectile, collision_mesh_identifiers)
+        .unwrap_or_else(|error| panic!("Could not spawn {}: {}", shot.projectile, error));
+    let mut projectile_entry = world.entry(projectile).unwrap();
+    projectile_entry.add_component(shot.position);
+    projectile_entry.add_component(shot.velocity);
+    if let Ok(asset) = projectile_entry.get_component_mut::<Asset>() {
+        asset.animation_start_time = game_time;
+    }
+}
+
+/// What a ray cast into the game hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RayTarget {
+    Entity(Entity),
+    /// Index of a triangle in the world collision mesh.
+    Triangle(#[allow(dead_code)] usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayCastHit {
+    pub hit: RayHit,
+    pub target: RayTarget,
+}
+
+///
+/// The game world and everything needed to advance it, one fixed-length tick at a time.
+///
+/// Knows nothing about windows, wall clock time or where its input comes from,
+/// so the same input events always lead to the same game.
+///
+pub struct Game {
+    /// The level the game started in, which is started over when the player dies.
+    level_path: PathBuf,
+    world: World,
+    player: Entity,
+    schedule: Schedule,
+    collision_schedule: Schedule,
+    resources: Resources,
+    extra_info: ExtraInfo,
+    prefabs: Prefabs,
+    collision_mesh_manager: CollisionMeshManager,
+    collision_mesh_identifiers: HashMap<String, CollisionMeshIdentifier>,
+    world_collision_mesh: WorldCollisionMesh,
+    /// How many times per tick overlapping entities are pushed apart.
+    solver_iterations: usize,
+    camera: Camera,
+    broadphase: SpatialHash,
+    /// Seconds of game time passed, which animation start times are relative to.
+    game_time: f32,
+}
+
+impl Game {
+    pub fn new(level_path: &Path, seed: u64, tick_seconds: f32) -> Result<Game, LevelError> {
+        let (collision_mesh_manager, collision_mesh_identifiers) = collision_meshes();
+
+        let prefabs = Prefabs::load(PREFABS_PATH, &collision_mesh_identifiers)
+            .map_err(LevelError::Prefabs)?;
+        let Level {
+            world,
+            player,
+            world_collision_mesh,
+            world_surfaces,
+            solver_iterations,
+            camera,
+        } = Level::load(level_path, &prefabs, &collision_mesh_identifiers)?;
+        let camera = Camera::new(camera, player_motion(&world, player).0);
+        let mut resources = setup_resources(seed, tick_seconds);
+        resources.insert(world_surfaces);
+        resources.insert(build_navigation_grid(
+            &world,
+            &collision_mesh_manager,
+            &world_collision_mesh,
+        ));
+
+        Ok(Game {
+            level_path: level_path.to_path_buf(),
+            world,
+            player,
+            schedule: setup_schedule(),
+            collision_schedule: setup_collision_schedule(),
+            resources,
+            extra_info: ExtraInfo::new(),
+            prefabs,
+            collision_mesh_manager,
+            collision_mesh_identifiers,
+            world_collision_mesh,
+            solver_iterations,
+            camera,
+            broadphase: SpatialHash::new(BROADPHASE_CELL_SIZE),
+            game_time: 0.0,
+        })
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn player(&self) -> Entity {
+        self.player
+    }
+
+    pub fn extra_info(&self) -> &ExtraInfo {
+        &self.extra_info
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn game_time(&self) -> f32 {
+        self.game_time
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        SaveGame::capture(
+            &self.world,
+            self.player,
+            &self.extra_info,
+            &self.resources.get::<ChaCha12Rng>().unwrap(),
+            self.game_time,
+            &self.collision_mesh_identifiers,
+        )
+        .write(path)
+    }
+
+    pub fn load(&mut self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let restored =
+            SaveGame::read(path)?.restore(&self.prefabs, &self.collision_mesh_identifiers)?;
+        self.world = restored.world;
+        self.player = restored.player;
+        self.extra_info = restored.extra_info;
+        self.resources.insert(restored.rng);
+        self.game_time = restored.time;
+        self.camera
+            .snap_to(player_motion(&self.world, self.player).0);
+        self.resources.insert(CollisionEvents::default());
+        self.resources.insert(build_navigation_grid(
+            &self.world,
+            &self.collision_mesh_manager,
+            &self.world_collision_mesh,
+        ));
+        Ok(())
+    }
+
+    ///
+    /// Advances the game by one tick, reacting to the given input events.
+    ///
+    pub fn tick(&mut self, events: Vec<StateInputEvent>) {
+        let world = &mut self.world;
+        let extra_info = &mut self.extra_info;
+
+        let (mut velocity, position) = if let Some(player_entry) = world.entry(self.player) {
+            (
+                *player_entry.get_component::<Velocity>().unwrap(),
+                *player_entry.get_component::<Position>().unwrap(),
+            )
+        } else {
+            panic!("The player has disappeared!");
+        };
+
+        {
+            // HANDLE INPUT EVENTS
+            for event in events {
+                match event {
+                    StateInputEvent::MovePlayerRelative { delta } => {
+                        velocity.dx += delta.x * extra_info.speed;
+                        velocity.dy += delta.y * extra_info.speed;
+                    }
+                    StateInputEvent::Jump => extra_info.shake += 5.0,
+                    StateInputEvent::Charge(_) => {
+                        extra_info.speed = 2.0;
+                        if extra_info.charge < 30 {
+                            extra_info.charge += 1;
+                        }
+                    }
+                    StateInputEvent::Shoot(direction) => {
+                        extra_info.speed = 16.0;
+                        if extra_info.charge > 10 {
+                            spawn_projectile(
+                                world,
+                                &self.prefabs,
+                                &self.collision_mesh_identifiers,
+                                Shot {
+                                    shooter: self.player,
+                                    projectile: "arrow".into(),
+                                    position,
+                                    velocity: Velocity::from(
+                                        Vec2::from(direction) * (32.0 * (extra_info.charge as f32)),
+                                    ),
+                                },
+                                self.game_time,
+                            );
+                        }
+                        extra_info.charge = 0;
+                    }
+                }
+            }
+        }
+
+        if let Some(mut player_entry) = world.entry(self.player) {
+            *player_entry.get_component_mut::<Velocity>().unwrap() = velocity;
+            *player_entry.get_component_mut::<Position>().unwrap() = position;
+        } else {
+            panic!("The player has disappeared!");
+        };
+
+        // Do world step
+        self.resources.insert(PlayerPosition {
+            position: Vec2::from(position),
+        });
+        self.schedule.execute(world, &mut self.resources);
+        let elapsed_seconds = self.resources.get::<Time>().unwrap().elapsed_seconds;
+        move_continuously(
+            world,
+            &self.collision_mesh_manager,
+            &self.world_collision_mesh,
+            elapsed_seconds,
+        );
+        self.game_time += elapsed_seconds;
+
+        // Fire what the AI shot this tick, if it can see the player
+        let shots: Vec<Shot> =
+            std::mem::take(&mut self.resources.get_mut::<Shots>().unwrap().shots)
+                .into_iter()
+                .filter(|shot| self.can_see(shot.shooter, self.player))
+                .collect();
+        let world = &mut self.world;
+        for shot in shots {
+            spawn_projectile(
+                world,
+                &self.prefabs,
+                &self.collision_mesh_identifiers,
+                shot,
+                self.game_time,
+            );
+        }
+
+        // Switch the states the AI asked for, which only act from the next tick on
+        let state_changes =
+            std::mem::take(&mut self.resources.get_mut::<StateChanges>().unwrap().changes);
+        for state_change in state_changes {
+            if let Some(mut entry) = world.entry(state_change.entity) {
+                enter_ai_state(
+                    &mut entry,
+                    &state_change.state,
+                    &self.collision_mesh_identifiers,
+                )
+                .unwrap_or_else(|error| {
+                    panic!("Could not enter AI state {}: {}", state_change.state, error)
+                });
+            }
+        }
+
+        // Remove entities whose lives are over
+        handle_timed_life(world);
+
+        //TODO: COLLISION
+        let colliders: Vec<(Entity, Collider, CollisionMesh)> =
+            <(Entity, &Position, &Collider)>::query()
+                .iter(world)
+                .map(|(entity, position, collider)| {
+                    (
+                        *entity,
+                        *collider,
+                        self.collision_mesh_manager.get_collision_mesh(
+                            collider.collision_mesh,
+                            Vec2::from(*position),
+                            collider.size,
+                        ),
+                    )
+                })
+                .collect();
+        let mut contacts =
+            find_contacts(&colliders, &mut self.broadphase, &self.world_collision_mesh);
+
+        // Let systems react to the contacts
+        self.resources
+            .get_mut::<CollisionEvents>()
+            .unwrap()
+            .update(contacts_both_ways(&colliders, &contacts));
+        self.collision_schedule.execute(world, &mut self.resources);
+
+        remove_sensor_contacts(world, &colliders, &mut contacts);
+        resolve_contacts(
+            world,
+            &colliders,
+            &contacts,
+            &self.world_collision_mesh,
+            self.solver_iterations,
+        );
+
+        handle_deaths(world, self.player, self.game_time);
+
+        self.extra_info.update();
+        let (player_position, player_velocity) = player_motion(world, self.player);
+        self.camera
+            .update(player_position, player_velocity, elapsed_seconds);
+
+        let player_health = world
+            .entry_ref(self.player)
+            .unwrap()
+            .get_component::<Health>()
+            .map(|health| health.hit_points);
+        if player_health.is_ok_and(|hit_points| hit_points <= 0.0) {
+            println!("The player has died, starting over");
+            self.restart();
+        }
+    }
+
+    ///
+    /// Returns what the ray first hits, out of the world collision mesh and the colliders of entities
+    /// for which `hits` returns true. Sensors are never hit.
+    ///
+    pub fn cast_ray(
+        &self,
+        ray: &Ray,
+        hits: impl Fn(&Entity, &Collider) -> bool,
+    ) -> Option<RayCastHit> {
+        let mut colliders =
+            <(Entity, &Position, &Collider)>::query().filter(!component::<Sensor>());
+        let entity_hit = colliders
+            .iter(&self.world)
+            .filter(|(entity, _, collider)| hits(entity, collider))
+            .filter_map(|(entity, position, collider)| {
+                let collision_mesh = self.collision_mesh_manager.get_collision_mesh(
+                    collider.collision_mesh,
+                    Vec2::from(*position),
+                    collider.size,
+                );
+                collision_mesh.shape.cast_ray(ray).map(|hit| RayCastHit {
+                    hit,
+                    target: RayTarget::Entity(*entity),
+                })
+            });
+        let triangle_hit = self
+            .world_collision_mesh
+            .cast_ray(ray)
+            .map(|(triangle, hit)| RayCastHit {
+                hit,
+                target: RayTarget::Triangle(triangle),
+            });
+        entity_hit.chain(triangle_hit).reduce(|hit_1, hit_2| {
+            if hit_2.hit.time < hit_1.hit.time {
+                hit_2
+            } else {
+                hit_1
+            }
+        })
+    }
+
+    ///
+    /// Whether nothing is in the way between the two entities,
+    /// ignoring the entities the viewer doesn't collide with.
+    ///
+    pub fn can_see(&self, viewer: Entity, target: Entity) -> bool {
+        let position = |entity| {
+            self.world
+                .entry_ref(entity)
+                .ok()
+                .and_then(|entry| entry.get_component::<Position>().ok().copied())
+        };
+        let (viewer_position, target_position) = match (position(viewer), position(target)) {
+            (Some(viewer_position), Some(target_position)) => (viewer_position, target_position),
+            _ => return false,
+        };
+        let viewer_collider = self
+            .world
+            .entry_ref(viewer)
+            .unwrap()
+            .get_component::<Collider>()
+            .ok()
+            .copied();
+
+        let ray = Ray::new(
+            Vec2::from(viewer_position),
+            Vec2::from(target_position) - Vec2::from(viewer_position),
+        );
+        let hit = self.cast_ray(&ray, |entity, collider| {
+            *entity != viewer
+                && viewer_collider
+                    .is_none_or(|viewer_collider| viewer_collider.can_collide(collider))
+        });
+        match hit {
+            Some(RayCastHit {
+                target: RayTarget::Entity(entity),
+                ..
+            }) => entity == target,
+            Some(_) => false,
+            // The ray ends at the target's position, inside any collider the target has
+            None => true,
+        }
+    }
+
+    /// Starts the level over, keeping the game time and random number generator going.
+    fn restart(&mut self) {
+        let Level {
+            world,
+            player,
+            world_collision_mesh,
+            world_surfaces,
+            solver_iterations,
+            camera,
+        } = Level::load(
+            &self.level_path,
+            &self.prefabs,
+            &self.collision_mesh_identifiers,
+        )
+        .unwrap_or_else(|error| {
+            panic!(
+                "Could not load level {}: {}",
+                self.level_path.display(),
+                error
+            )
+        });
+        self.world = world;
+        self.player = player;
+        self.world_collision_mesh = world_collision_mesh;
+        self.solver_iterations = solver_iterations;
+        self.camera = Camera::new(camera, player_motion(&self.world, self.player).0);
+        self.resources.insert(world_surfaces);
+        self.resources.insert(build_navigation_grid(
+            &self.world,
+            &self.collision_mesh_manager,
+            &self.world_collision_mesh,
+        ));
+        self.extra_info = ExtraInfo::new();
+        self.resources.insert(CollisionEvents::default());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{
+        collision::{CollisionLayer, CollisionLayers, Triangle},
+        resources::CollisionEvent,
+    };
+    use super::*;
+
+    fn dead(death_animation: Option<DeathAnimation>) -> Health {
+        Health {
+            hit_points: 0.0,
+            max_hit_points: None,
+            invulnerability_seconds: 0.0,
+            invulnerable_seconds_left: 0.0,
+            death_animation,
+        }
+    }
+
+    #[test]
+    fn fast_entities_stop_at_thin_colliders_and_world_triangles() {
+        let mut collision_mesh_manager = CollisionMeshManager::new();
+        let circle = circle(&mut collision_mesh_manager);
+        let wall =
+            collision_mesh_manager.add_collision_mesh(CollisionMesh::new(Shape::Aabb(Aabb {
+                min_x: -1.0,
+                min_y: -50.0,
+                max_x: 1.0,
+                max_y: 50.0,
+            })));
+        let arrow = |world: &mut World, y: f32| {
+            world.push((
+                Position { x: 0.0, y },
+                Velocity {
+                    dx: 1000.0,
+                    dy: 0.0,
+                },
+                Collider {
+                    collision_mesh: circle,
+                    size: 4.0,
+                    layers: CollisionLayer::PROJECTILE.into(),
+                    collides_with: vec![CollisionLayer::PROP, CollisionLayer::WORLD].into(),
+                },
+                ContinuousCollision {},
+            ))
+        };
+        let mut world = World::default();
+        let at_wall = arrow(&mut world, 0.0);
+        let at_triangle = arrow(&mut world, 200.0);
+        let in_the_open = arrow(&mut world, 400.0);
+        world.push((
+            Position { x: 51.0, y: 0.0 },
+            Collider {
+                collision_mesh: wall,
+                size: 1.0,
+                layers: CollisionLayer::PROP.into(),
+                collides_with: CollisionLayers::ALL,
+            },
+        ));
+        let world_collision_mesh = WorldCollisionMesh::new(vec![Triangle::new(
+            glm::vec2(50.0, 150.0),
+            glm::vec2(52.0, 250.0),
+            glm::vec2(50.0, 250.0),
+        )]);
+
+        // Without stopping, every arrow would pass all the way through in this one tick
+        move_continuously(
+            &mut world,
+            &collision_mesh_manager,
+            &world_collision_mesh,
+            0.1,
+        );
+
+        let x = |entity| {
+            world
+                .entry_ref(entity)
+                .unwrap()
+                .get_component::<Position>()
+                .unwrap()
+                .x
+        };
+        // The arrows touch at 46 and go just past it, so the collision pass finds them overlapping
+        assert_eq!(x(at_wall), 46.0 + CONTINUOUS_COLLISION_PENETRATION);
+        assert_eq!(x(at_triangle), 46.0 + CONTINUOUS_COLLISION_PENETRATION);
+        assert_eq!(x(in_the_open), 100.0);
+    }
+
+    /// The colliders of the world, the way `Game::tick` gathers them.
+    fn colliders(
+        world: &World,
+        collision_mesh_manager: &CollisionMeshManager,
+    ) -> Vec<(Entity, Collider, CollisionMesh)> {
+        <(Entity, &Position, &Collider)>::query()
+            .iter(world)
+            .map(|(entity, position, collider)| {
+                (
+                    *entity,
+                    *collider,
+                    collision_mesh_manager.get_collision_mesh(
+                        collider.collision_mesh,
+                        Vec2::from(*position),
+                        collider.size,
+                    ),
+                )
+            })
+            .collect()
+    }
+
+    fn circle(collision_mesh_manager: &mut CollisionMeshManager) -> CollisionMeshIdentifier {
+        collision_mesh_manager.add_collision_mesh(CollisionMesh::new(Shape::Circle {
+            center: Vec2::zeros(),
+            radius: 1.0,
+        }))
+    }
+
+    fn position(world: &World, entity: Entity) -> Position {
+        *world
+            .entry_ref(entity)
+            .unwrap()
+            .get_component::<Position>()
+            .unwrap()
+    }
+
+    #[test]
+    fn sensors_report_overlaps_without_being_pushed() {
+        let mut collision_mesh_manager = CollisionMeshManager::new();
+        let circle = circle(&mut collision_mesh_manager);
+        let body = |layer: CollisionLayer| Collider {
+            collision_mesh: circle,
+            size: 10.0,
+            layers: layer.into(),
+            collides_with: CollisionLayers::ALL,
+        };
+        let still = || Velocity { dx: 0.0, dy: 0.0 };
+        let mut world = World::default();
+        let sensor = world.push((
+            Position { x: 0.0, y: 0.0 },
+            Collider {
+                collides_with: CollisionLayer::PLAYER.into(),
+                ..body(CollisionLayer::SENSOR)
+            },
+            Sensor {},
+        ));
+        let in_sensor = world.push((
+            Position { x: 5.0, y: 0.0 },
+            still(),
+            body(CollisionLayer::PLAYER),
+        ));
+        // The same overlap between solid bodies does push them apart
+        let pushed = world.push((
+            Position { x: 0.0, y: 100.0 },
+            still(),
+            body(CollisionLayer::ENEMY),
+        ));
+        let pushing = world.push((
+            Position { x: 5.0, y: 100.0 },
+            still(),
+            body(CollisionLayer::PLAYER),
+        ));
+        let world_collision_mesh = WorldCollisionMesh::new(Vec::new());
+        let colliders = colliders(&world, &collision_mesh_manager);
+
+        let mut contacts = find_contacts(
+            &colliders,
+            &mut SpatialHash::new(BROADPHASE_CELL_SIZE),
+            &world_collision_mesh,
+        );
+        let mut collision_events = CollisionEvents::default();
+        collision_events.update(contacts_both_ways(&colliders, &contacts));
+        for (entity, other) in [(sensor, in_sensor), (in_sensor, sensor)] {
+            assert!(collision_events.events.contains(&CollisionEvent {
+                contact: Contact {
+                    entity,
+                    other: Some(other),
+                },
+                phase: ContactPhase::Begin,
+            }));
+        }
+
+        remove_sensor_contacts(&world, &colliders, &mut contacts);
+        resolve_contacts(&mut world, &colliders, &contacts, &world_collision_mesh, 4);
+        assert_eq!(position(&world, sensor), Position { x: 0.0, y: 0.0 });
+        assert_eq!(position(&world, in_sensor), Position { x: 5.0, y: 0.0 });
+        assert!(position(&world, pushing).x - position(&world, pushed).x >= 20.0 - MIN_PENETRATION);
+    }
+
+    #[test]
+    fn layers_only_collide_when_both_colliders_want_to() {
+        let mut collision_mesh_manager = CollisionMeshManager::new();
+        let circle = circle(&mut collision_mesh_manager);
+        let collider = |layer: CollisionLayer, collides_with: Vec<CollisionLayer>| Collider {
+            collision_mesh: circle,
+            size: 10.0,
+            layers: layer.into(),
+            collides_with: collides_with.into(),
+        };
+        let arrow = collider(
+            CollisionLayer::PROJECTILE,
+            vec![CollisionLayer::PROP, CollisionLayer::AMBIENT],
+        );
+        let bush = collider(CollisionLayer::PROP, vec![CollisionLayer::PROJECTILE]);
+        let firefly = collider(
+            CollisionLayer::AMBIENT,
+            vec![CollisionLayer::PROP, CollisionLayer::WORLD],
+        );
+        assert!(arrow.can_collide(&bush) && bush.can_collide(&arrow));
+        // The arrow would hit the firefly, but the firefly only bumps into the scenery
+        assert!(!arrow.can_collide(&firefly) && !firefly.can_collide(&arrow));
+        assert!(firefly.collides_with_world() && !arrow.collides_with_world());
+
+        let mut world = World::default();
+        let arrow = world.push((Position { x: 0.0, y: 0.0 }, arrow));
+        let bush = world.push((Position { x: 5.0, y: 0.0 }, bush));
+        let firefly = world.push((Position { x: 0.0, y: 5.0 }, firefly));
+        let world_collision_mesh = WorldCollisionMesh::new(vec![Triangle::new(
+            glm::vec2(-20.0, -20.0),
+            glm::vec2(20.0, -20.0),
+            glm::vec2(0.0, 20.0),
+        )]);
+        let colliders = colliders(&world, &collision_mesh_manager);
+
+        let contacts = contacts_both_ways(
+            &colliders,
+            &find_contacts(
+                &colliders,
+                &mut SpatialHash::new(BROADPHASE_CELL_SIZE),
+                &world_collision_mesh,
+            ),
+        );
+        let touching = |entity: Entity| -> HashSet<Option<Entity>> {
+            contacts
+                .iter()
+                .filter(|contact| contact.entity == entity)
+                .map(|contact| contact.other)
+                .collect()
+        };
+        assert_eq!(touching(arrow), HashSet::from([Some(bush)]));
+        assert_eq!(touching(bush), HashSet::from([Some(arrow)]));
+        assert_eq!(touching(firefly), HashSet::from([None]));
+    }
+
+    fn velocity(world: &World, entity: Entity) -> Vec2 {
+        Vec2::from(
+            *world
+                .entry_ref(entity)
+                .unwrap()
+                .get_component::<Velocity>()
+                .unwrap(),
+        )
+    }
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).norm() < 0.001,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    /// A ball of radius 10, and a wall 2 wide and 100 high, for the contact tests.
+    fn ball_and_wall(collision_mesh_manager: &mut CollisionMeshManager) -> (Collider, Collider) {
+        let ball = Collider {
+            collision_mesh: circle(collision_mesh_manager),
+            size: 10.0,
+            layers: CollisionLayers::ALL,
+            collides_with: CollisionLayers::ALL,
+        };
+        let wall = Collider {
+            collision_mesh: collision_mesh_manager.add_collision_mesh(CollisionMesh::new(
+                Shape::Aabb(Aabb {
+                    min_x: -1.0,
+                    min_y: -50.0,
+                    max_x: 1.0,
+                    max_y: 50.0,
+                }),
+            )),
+            size: 1.0,
+            ..ball
+        };
+        (ball, wall)
+    }
+
+    #[test]
+    fn lighter_bodies_are_pushed_further() {
+        let mut collision_mesh_manager = CollisionMeshManager::new();
+        let (ball, _) = ball_and_wall(&mut collision_mesh_manager);
+        let body = |mass: f32| RigidBody {
+            mass,
+            ..RigidBody::default()
+        };
+        let still = Velocity { dx: 0.0, dy: 0.0 };
+        let mut world = World::default();
+        let heavy = world.push((Position { x: 0.0, y: 0.0 }, still, ball, body(3.0)));
+        let light = world.push((Position { x: 16.0, y: 0.0 }, still, ball, body(1.0)));
+        let colliders = colliders(&world, &collision_mesh_manager);
+
+        resolve_contacts(
+            &mut world,
+            &colliders,
+            &[(0, Some(1))],
+            &WorldCollisionMesh::new(Vec::new()),
+            4,
+        );
+
+        // The overlap of 4 is split 1 to 3
+        assert_near(Vec2::from(position(&world, heavy)), glm::vec2(-1.0, 0.0));
+        assert_near(Vec2::from(position(&world, light)), glm::vec2(19.0, 0.0));
+        assert_near(velocity(&world, heavy), Vec2::zeros());
+        assert_near(velocity(&world, light), Vec2::zeros());
+    }
+
+    #[test]
+    fn bodies_bounce_off_and_slide_along_with_friction() {
+        let mut collision_mesh_manager = CollisionMeshManager::new();
+        let (ball, wall) = ball_and_wall(&mut collision_mesh_manager);
+        let body = |restitution: f32, friction: f32| RigidBody {
+            mass: 1.0,
+            restitution,
+            friction,
+        };
+        let mut world = World::default();
+        let hitting = Velocity { dx: 10.0, dy: 5.0 };
+        let slowed = world.push((Position { x: 0.0, y: 0.0 }, hitting, ball, body(0.5, 0.2)));
+        let slowing_wall = world.push((Position { x: 10.0, y: 0.0 }, wall, body(0.0, 0.2)));
+        let stopped = world.push((Position { x: 0.0, y: 200.0 }, hitting, ball, body(0.5, 1.0)));
+        let stopping_wall = world.push((Position { x: 10.0, y: 200.0 }, wall, body(0.0, 1.0)));
+        let colliders = colliders(&world, &collision_mesh_manager);
+        let index = |entity: Entity| {
+            colliders
+                .iter()
+                .position(|(collider, _, _)| *collider == entity)
+                .unwrap()
+        };
+        let contacts = [
+            (index(slowed), Some(index(slowing_wall))),
+            (index(stopped), Some(index(stopping_wall))),
+        ];
+
+        resolve_contacts(
+            &mut world,
+            &colliders,
+            &contacts,
+            &WorldCollisionMesh::new(Vec::new()),
+            4,
+        );
+
+        // Half the speed into the wall bounces back, and friction takes 0.2 of the bounce's impulse of 15 off the sliding
+        assert_near(velocity(&world, slowed), glm::vec2(-5.0, 2.0));
+        // Friction stops the sliding without reversing it
+        assert_near(velocity(&world, stopped), glm::vec2(-5.0, 0.0));
+    }
+
+    #[test]
+    fn static_bodies_never_move() {
+        let mut collision_mesh_manager = CollisionMeshManager::new();
+        let (ball, wall_collider) = ball_and_wall(&mut collision_mesh_manager);
+        let mut world = World::default();
+        let moving = world.push((
+            Position { x: 0.0, y: 0.0 },
+            Velocity { dx: 10.0, dy: 0.0 },
+            ball,
+        ));
+        // Static bodies don't move, whether or not they have a rigid body
+        let wall = world.push((
+            Position { x: 10.0, y: 0.0 },
+            wall_collider,
+            RigidBody {
+                mass: 0.001,
+                ..RigidBody::default()
+            },
+        ));
+        let other_wall = world.push((Position { x: 11.0, y: 0.0 }, wall_collider));
+        let on_triangle = world.push((Position { x: 200.0, y: 0.0 }, ball, RigidBody::default()));
+        let world_collision_mesh = WorldCollisionMesh::new(vec![Triangle::new(
+            glm::vec2(190.0, -20.0),
+            glm::vec2(230.0, -20.0),
+            glm::vec2(210.0, 20.0),
+        )]);
+        let colliders = colliders(&world, &collision_mesh_manager);
+        let index = |entity: Entity| {
+            colliders
+                .iter()
+                .position(|(collider, _, _)| *collider == entity)
+                .unwrap()
+        };
+
+        resolve_contacts(
+            &mut world,
+            &colliders,
+            &[
+                (index(moving), Some(index(wall))),
+                (index(wall), Some(index(other_wall))),
+                (index(on_triangle), None),
+            ],
+            &world_collision_mesh,
+            4,
+        );
+
+        // Only the moving entity gets pushed out, all the way
+        assert_near(Vec2::from(position(&world, moving)), glm::vec2(-1.0, 0.0));
+        assert_eq!(position(&world, wall), Position { x: 10.0, y: 0.0 });
+        assert_eq!(position(&world, other_wall), Position { x: 11.0, y: 0.0 });
+        assert_eq!(position(&world, on_triangle), Position { x: 200.0, y: 0.0 });
+    }
+
+    #[test]
+    fn dead_entities_are_removed_or_play_their_death_animation() {
+        let mut world = World::default();
+        let player = world.push((dead(None),));
+        let removed = world.push((dead(None),));
+        let chase = AiChase {
+            speed: 100.0,
+            sight_range: 500.0,
+        };
+        let animated = world.push((
+            dead(Some(DeathAnimation {
+                animation: 2,
+                seconds: 0.5,
+            })),
+            Damage {
+                amount: 1.0,
+                removed_on_hit: false,
+            },
+            chase,
+            Asset {
+                name: "bush".into(),
+                animation: 0,
+                animation_start_time: 0.0,
+            },
+        ));
+        let alive = world.push((
+            Health {
+                hit_points: 1.0,
+                ..dead(None)
+            },
+            chase,
+        ));
+
+        handle_deaths(&mut world, player, 10.0);
+
+        // The player's death is handled elsewhere
+        assert!(world.entry_ref(player).is_ok());
+        assert!(world.entry_ref(removed).is_err());
+        assert!(world
+            .entry_ref(alive)
+            .unwrap()
+            .get_component::<AiChase>()
+            .is_ok());
+
+        let animated = world.entry_ref(animated).unwrap();
+        assert!(animated.get_component::<Health>().is_err());
+        assert!(animated.get_component::<Damage>().is_err());
+        assert!(animated.get_component::<AiChase>().is_err());
+        let asset = animated.get_component::<Asset>().unwrap();
+        assert_eq!((asset.animation, asset.animation_start_time), (2, 10.0));
+        assert_eq!(
+            animated.get_component::<TimedLife>().unwrap().seconds_left,
+            0.5
+        );
+    }
+}
@@ -0,0 +1,300 @@
+use std::{collections::BTreeMap, fmt, path::PathBuf, sync::mpsc, time::Instant};
+
+use legion::*;
+use serde::Deserialize;
+
+use super::{
+    game::Game,
+    logic::{InputPipeline, LogicConfig},
+    *,
+};
+use crate::graphics::ExternalEvent;
+
+/// How to run the game without a window.
+pub struct HeadlessConfig {
+    pub ticks: usize,
+    /// External events to feed the game, in place of a window.
+    pub script_path: Option<PathBuf>,
+    /// Saves the final world to this file, in the save format.
+    pub output_path: Option<PathBuf>,
+}
+
+///
+/// The layout of an input script: external events, like key presses,
+/// and the tick they happen before.
+///
+/// ```toml
+/// [[events]]
+/// tick = 0
+/// KeyboardInput = { key_code = "D", state = "Pressed" }
+/// ```
+///
+#[derive(Deserialize)]
+struct InputScript {
+    #[serde(default)]
+    events: Vec<ScriptedEvent>,
+}
+
+#[derive(Deserialize)]
+struct ScriptedEvent {
+    tick: usize,
+    #[serde(flatten)]
+    event: ExternalEvent,
+}
+
+/// A summary of the world after a headless run.
+pub struct HeadlessReport {
+    pub ticks: usize,
+    pub game_time: f32,
+    pub entities: usize,
+    /// How many entities are drawn with each asset.
+    pub assets: BTreeMap<String, usize>,
+    pub player_position: Position,
+}
+
+impl fmt::Display for HeadlessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "After {} ticks ({:.2} seconds of game time):",
+            self.ticks, self.game_time
+        )?;
+        writeln!(
+            f,
+            "  player at ({:.2}, {:.2})",
+            self.player_position.x, self.player_position.y
+        )?;
+        writeln!(f, "  {} entities", self.entities)?;
+        for (asset, count) in self.assets.iter() {
+            writeln!(f, "    {} {}", count, asset)?;
+        }
+        Ok(())
+    }
+}
+
+impl HeadlessReport {
+    fn new(game: &Game, ticks: usize) -> Self {
+        let world = game.world();
+        let mut assets = BTreeMap::new();
+        for asset in <&Asset>::query().iter(world) {
+            *assets.entry(asset.name.clone()).or_insert(0) += 1;
+        }
+        Self {
+            ticks,
+            game_time: game.game_time(),
+            entities: world.len(),
+            assets,
+            player_position: *world
+                .entry_ref(game.player())
+                .unwrap()
+                .get_component::<Position>()
+                .unwrap(),
+        }
+    }
+}
+
+///
+/// Runs the game as fast as possible without a window, feeding it scripted external events,
+/// and reports what the world looks like in the end.
+///
+pub fn run_headless(config: LogicConfig, headless_config: HeadlessConfig) -> HeadlessReport {
+    let (mut game, mut input, _) = InputPipeline::start(&config);
+
+    let mut script = match &headless_config.script_path {
+        Some(path) => {
+            let contents = std::fs::read_to_string(path).unwrap_or_else(|error| {
+                panic!("Could not read input script {}: {}", path.display(), error)
+            });
+            let script: InputScript = toml::from_str(&contents).unwrap_or_else(|error| {
+                panic!("Could not parse input script {}: {}", path.display(), error)
+            });
+            script.events
+        }
+        None => Vec::new(),
+    };
+    script.sort_by_key(|scripted_event| std::cmp::Reverse(scripted_event.tick));
+
+    // The external event handler reads from a channel, just as when events come from a window.
+    let (event_sender, event_receiver) = mpsc::channel();
+
+    let start_time = Instant::now();
+    for tick in 0..headless_config.ticks {
+        while script
+            .last()
+            .is_some_and(|scripted_event| scripted_event.tick <= tick)
+        {
+            event_sender.send(script.pop().unwrap().event).unwrap();
+        }
+        input.handle_inputs(&event_receiver, &mut game);
+        let events = input.tick_events();
+        game.tick(events);
+    }
+    println!(
+        "Simulated {} ticks in {:?}",
+        headless_config.ticks,
+        start_time.elapsed()
+    );
+
+    if let Some(path) = &headless_config.output_path {
+        match game.save(path) {
+            Ok(()) => println!("Saved the final world to {}", path.display()),
+            Err(error) => println!("Could not save the final world: {}", error),
+        }
+    }
+
+    HeadlessReport::new(&game, headless_config.ticks)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::*;
+    use crate::logic::{logic::DEFAULT_TICKS_PER_SECOND, state_input_event::StateInputEvent};
+
+    const LEVEL: &str = "assets/levels/meadow.toml";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("a_little_guy_{}_{}", std::process::id(), name))
+    }
+
+    fn config(record_path: Option<PathBuf>, replay_path: Option<PathBuf>) -> LogicConfig {
+        LogicConfig {
+            level_path: LEVEL.into(),
+            ticks_per_second: DEFAULT_TICKS_PER_SECOND,
+            record_path,
+            replay_path,
+        }
+    }
+
+    /// Walks up for a while, then right, charging up a shot to the right on the way.
+    fn write_script(path: &Path) {
+        std::fs::write(
+            path,
+            r#"
+            [[events]]
+            tick = 0
+            KeyboardInput = { key_code = "W", state = "Pressed" }
+
+            [[events]]
+            tick = 20
+            KeyboardInput = { key_code = "W", state = "Released" }
+
+            [[events]]
+            tick = 20
+            KeyboardInput = { key_code = "D", state = "Pressed" }
+
+            [[events]]
+            tick = 20
+            KeyboardInput = { key_code = "Right", state = "Pressed" }
+
+            [[events]]
+            tick = 35
+            KeyboardInput = { key_code = "Right", state = "Released" }
+            "#,
+        )
+        .unwrap();
+    }
+
+    /// The input events for a tick of a run that moves around in circles, charging up and shooting arrows.
+    fn tick_events(tick: usize) -> Vec<StateInputEvent> {
+        let mut events = vec![StateInputEvent::MovePlayerRelative {
+            delta: glm::vec2((tick as f32 * 0.3).cos(), (tick as f32 * 0.3).sin()),
+        }];
+        match tick % 30 {
+            0..=11 => events.push(StateInputEvent::Charge(Direction::Right)),
+            12 => events.push(StateInputEvent::Shoot(Direction::Right)),
+            _ => {}
+        }
+        events
+    }
+
+    #[test]
+    fn scripted_input_moves_the_player() {
+        let script_path = temp_path("script.toml");
+        write_script(&script_path);
+
+        let report = run_headless(
+            config(None, None),
+            HeadlessConfig {
+                ticks: 37,
+                script_path: Some(script_path.clone()),
+                output_path: None,
+            },
+        );
+        std::fs::remove_file(script_path).unwrap();
+
+        assert_eq!(report.ticks, 37);
+        assert!((report.game_time - 37.0 / DEFAULT_TICKS_PER_SECOND).abs() < 0.001);
+        assert!(report.player_position.x > 0.0);
+        assert!(report.player_position.y != 0.0);
+        assert_eq!(report.assets.get("player"), Some(&1));
+        assert_eq!(report.assets.get("arrow"), Some(&1));
+    }
+
+    #[test]
+    fn replays_reproduce_the_recorded_run() {
+        let script_path = temp_path("replayed_script.toml");
+        let replay_path = temp_path("replay.toml");
+        let recorded_path = temp_path("recorded.toml");
+        let replayed_path = temp_path("replayed.toml");
+        write_script(&script_path);
+
+        run_headless(
+            config(Some(replay_path.clone()), None),
+            HeadlessConfig {
+                ticks: 90,
+                script_path: Some(script_path.clone()),
+                output_path: Some(recorded_path.clone()),
+            },
+        );
+        run_headless(
+            config(None, Some(replay_path.clone())),
+            HeadlessConfig {
+                ticks: 90,
+                script_path: None,
+                output_path: Some(replayed_path.clone()),
+            },
+        );
+
+        let recorded = std::fs::read_to_string(&recorded_path).unwrap();
+        let replayed = std::fs::read_to_string(&replayed_path).unwrap();
+        for path in [script_path, replay_path, recorded_path, replayed_path] {
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(recorded, replayed);
+    }
+
+    #[test]
+    fn loading_a_save_continues_the_game_the_same_way() {
+        let tick_seconds = 1.0 / DEFAULT_TICKS_PER_SECOND;
+        let save_path = temp_path("midway.toml");
+        let saved_path = temp_path("continued_from_saving.toml");
+        let loaded_path = temp_path("continued_from_loading.toml");
+
+        // Seeds above `i64::MAX` don't fit in a TOML integer as they are
+        let mut game = Game::new(Path::new(LEVEL), u64::MAX - 1, tick_seconds).unwrap();
+        for tick in 0..45 {
+            game.tick(tick_events(tick));
+        }
+        game.save(&save_path).unwrap();
+        for tick in 45..90 {
+            game.tick(tick_events(tick));
+        }
+        game.save(&saved_path).unwrap();
+
+        let mut loaded_game = Game::new(Path::new(LEVEL), 0, tick_seconds).unwrap();
+        loaded_game.load(&save_path).unwrap();
+        for tick in 45..90 {
+            loaded_game.tick(tick_events(tick));
+        }
+        loaded_game.save(&loaded_path).unwrap();
+
+        let saved = std::fs::read_to_string(&saved_path).unwrap();
+        let loaded = std::fs::read_to_string(&loaded_path).unwrap();
+        for path in [save_path, saved_path, loaded_path] {
+            std::fs::remove_file(path).unwrap();
+        }
+        assert_eq!(saved, loaded);
+    }
+}
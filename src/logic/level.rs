@@ -0,0 +1,408 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use glm::Vec2;
+use legion::{Entity, World};
+use serde::Deserialize;
+
+use super::{
+    camera::CameraSettings,
+    collision::{
+        triangulate, CollisionMeshIdentifier, Mask, SurfaceMaterial, Triangle, WorldCollisionMesh,
+        WorldSurfaces,
+    },
+    prefab::{EntityError, PrefabError, Prefabs},
+};
+
+///
+/// A level loaded from a level file: the world with all its entities, which of them is the player,
+/// the static collision geometry, the materials of the ground, and how thoroughly overlapping entities are pushed apart.
+///
+pub struct Level {
+    pub world: World,
+    pub player: Entity,
+    pub world_collision_mesh: WorldCollisionMesh,
+    pub world_surfaces: WorldSurfaces,
+    pub solver_iterations: usize,
+    pub camera: CameraSettings,
+}
+
+/// Everything that can go wrong while loading a level file.
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    /// The prefabs the level's entities are built from could not be loaded.
+    Prefabs(PrefabError),
+    /// An entity of the level could not be built.
+    Entity {
+        entity: usize,
+        error: EntityError,
+    },
+    /// The level must contain exactly one entity marked as the player.
+    PlayerCount(usize),
+    /// A collision polygon of the level could not be split into triangles.
+    Polygon(usize),
+    CollisionMask(image::ImageError),
+    /// A polygon traced from the collision mask could not be split into triangles.
+    TracedPolygon(usize),
+    /// A camera setting is out of its range.
+    CameraSetting {
+        setting: &'static str,
+        value: f32,
+    },
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(error) => write!(f, "could not read file: {}", error),
+            LevelError::Parse(error) => write!(f, "could not parse file: {}", error),
+            LevelError::Prefabs(error) => write!(f, "could not load prefabs: {}", error),
+            LevelError::Entity { entity, error } => write!(f, "entity #{}: {}", entity, error),
+            LevelError::PlayerCount(count) => write!(
+                f,
+                "exactly one entity must be marked as the player, found {}",
+                count
+            ),
+            LevelError::Polygon(polygon) => write!(
+                f,
+                "collision polygon #{} must have at least 3 corners, not cross itself, and have its holes inside it without touching it or each other",
+                polygon
+            ),
+            LevelError::CollisionMask(error) => {
+                write!(f, "could not load collision mask: {}", error)
+            }
+            LevelError::TracedPolygon(polygon) => write!(
+                f,
+                "polygon #{} traced from the collision mask crosses itself or its holes, try a smaller tolerance",
+                polygon
+            ),
+            LevelError::CameraSetting { setting, value } => {
+                write!(f, "camera setting `{}` can't be {}", setting, value)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+/// The layout of a level file.
+#[derive(Deserialize)]
+struct LevelDescription {
+    #[serde(default)]
+    entities: Vec<toml::value::Table>,
+    #[serde(default)]
+    world_collision: Vec<TriangleDescription>,
+    /// Collision polygons are given in pixels of the level's image if it has a transform, or in world coordinates.
+    image_transform: Option<ImageTransform>,
+    #[serde(default)]
+    collision_polygons: Vec<PolygonDescription>,
+    collision_mask: Option<CollisionMaskDescription>,
+    #[serde(default)]
+    surfaces: Vec<SurfaceDescription>,
+    /// How many times per tick overlapping entities are pushed apart. Crowded levels settle better with more.
+    #[serde(default = "default_solver_iterations")]
+    solver_iterations: usize,
+    #[serde(default)]
+    camera: CameraSettings,
+}
+
+fn default_solver_iterations() -> usize {
+    4
+}
+
+/// A world collision triangle in world coordinates, given counter clockwise.
+#[derive(Deserialize)]
+struct TriangleDescription {
+    a: Vec2,
+    b: Vec2,
+    c: Vec2,
+}
+
+/// Maps pixels of the level's image, with y pointing down, to world coordinates, with y pointing up.
+#[derive(Deserialize)]
+struct ImageTransform {
+    /// The pixel at the world's origin.
+    origin: Vec2,
+    /// World units per pixel.
+    scale: f32,
+}
+
+impl ImageTransform {
+    fn to_world(&self, pixel: Vec2) -> Vec2 {
+        let offset = (pixel - self.origin) * self.scale;
+        glm::vec2(offset.x, -offset.y)
+    }
+}
+
+///
+/// An image whose opaque pixels are solid, traced into collision polygons when the level is loaded.
+/// It is placed in the world with the level's image transform, or with one pixel per world unit around the origin.
+///
+#[derive(Deserialize)]
+struct CollisionMaskDescription {
+    /// Relative to the directory of the level file.
+    path: PathBuf,
+    /// How far in pixels the traced polygons may stray from the sides of the pixels, to keep them simple.
+    #[serde(default = "default_mask_tolerance")]
+    tolerance: f32,
+}
+
+fn default_mask_tolerance() -> f32 {
+    1.0
+}
+
+/// A simple polygon of world collision, split into triangles when the level is loaded.
+#[derive(Deserialize)]
+struct PolygonDescription {
+    outline: Vec<Vec2>,
+    #[serde(default)]
+    holes: Vec<Vec<Vec2>>,
+}
+
+/// A triangle of ground made of the material, in world coordinates and given counter clockwise.
+#[derive(Deserialize)]
+struct SurfaceDescription {
+    a: Vec2,
+    b: Vec2,
+    c: Vec2,
+    material: SurfaceMaterial,
+}
+
+impl Level {
+    pub fn load(
+        path: impl AsRef<Path>,
+        prefabs: &Prefabs,
+        collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+    ) -> Result<Level, LevelError> {
+        let contents = std::fs::read_to_string(&path).map_err(LevelError::Io)?;
+        let directory = path.as_ref().parent().unwrap_or_else(|| Path::new(""));
+        Self::parse(&contents, directory, prefabs, collision_mesh_identifiers)
+    }
+
+    /// Parses the contents of a level file, whose other files are found relative to the directory.
+    pub fn parse(
+        contents: &str,
+        directory: &Path,
+        prefabs: &Prefabs,
+        collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+    ) -> Result<Level, LevelError> {
+        let description: LevelDescription = toml::from_str(contents).map_err(LevelError::Parse)?;
+        if let Some((setting, value)) = description.camera.invalid_setting() {
+            return Err(LevelError::CameraSetting { setting, value });
+        }
+
+        let mut world = World::default();
+        let mut players = Vec::new();
+        for (index, mut table) in description.entities.into_iter().enumerate() {
+            let is_player = match table.remove("player") {
+                Some(value) => value.try_into().map_err(|error| LevelError::Entity {
+                    entity: index,
+                    error: EntityError::Component {
+                        component: "player".into(),
+                        error,
+                    },
+                })?,
+                None => false,
+            };
+            let entity = prefabs
+                .spawn(&mut world, table, collision_mesh_identifiers)
+                .map_err(|error| LevelError::Entity {
+                    entity: index,
+                    error,
+                })?;
+            if is_player {
+                players.push(entity);
+            }
+        }
+        if players.len() != 1 {
+            return Err(LevelError::PlayerCount(players.len()));
+        }
+
+        let mut triangles: Vec<Triangle> = description
+            .world_collision
+            .into_iter()
+            .map(|triangle| Triangle::new(triangle.a, triangle.b, triangle.c))
+            .collect();
+        let to_world = |corners: &Vec<Vec2>| -> Vec<Vec2> {
+            match &description.image_transform {
+                Some(image_transform) => corners
+                    .iter()
+                    .map(|corner| image_transform.to_world(*corner))
+                    .collect(),
+                None => corners.clone(),
+            }
+        };
+        for (index, polygon) in description.collision_polygons.iter().enumerate() {
+            let holes: Vec<Vec<Vec2>> = polygon.holes.iter().map(to_world).collect();
+            triangles.extend(
+                triangulate(&to_world(&polygon.outline), &holes)
+                    .ok_or(LevelError::Polygon(index))?,
+            );
+        }
+        if let Some(collision_mask) = &description.collision_mask {
+            let mask = Mask::load(directory.join(&collision_mask.path))
+                .map_err(LevelError::CollisionMask)?;
+            let one_pixel_per_unit = ImageTransform {
+                origin: Vec2::zeros(),
+                scale: 1.0,
+            };
+            let image_transform = description
+                .image_transform
+                .as_ref()
+                .unwrap_or(&one_pixel_per_unit);
+            let pixels_to_world = |corners: &Vec<Vec2>| -> Vec<Vec2> {
+                corners
+                    .iter()
+                    .map(|corner| image_transform.to_world(*corner))
+                    .collect()
+            };
+            for (index, polygon) in mask.trace(collision_mask.tolerance).iter().enumerate() {
+                let holes: Vec<Vec<Vec2>> = polygon.holes.iter().map(pixels_to_world).collect();
+                triangles.extend(
+                    triangulate(&pixels_to_world(&polygon.outline), &holes)
+                        .ok_or(LevelError::TracedPolygon(index))?,
+                );
+            }
+        }
+
+        Ok(Level {
+            world,
+            player: players[0],
+            world_collision_mesh: WorldCollisionMesh::new(triangles),
+            world_surfaces: WorldSurfaces::new(
+                description
+                    .surfaces
+                    .into_iter()
+                    .map(|surface| {
+                        (
+                            Triangle::new(surface.a, surface.b, surface.c),
+                            surface.material,
+                        )
+                    })
+                    .collect(),
+            ),
+            solver_iterations: description.solver_iterations,
+            camera: description.camera,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::game::collision_meshes;
+    use super::*;
+
+    /// A player without a collider, so levels can be parsed without any collision meshes.
+    const PLAYER: &str = "[[entities]]\nplayer = true\nposition = { x = 0.0, y = 0.0 }\n";
+
+    fn parse(contents: &str) -> Result<Level, LevelError> {
+        let prefabs = Prefabs::load("assets/prefabs.toml", &collision_meshes().1).unwrap();
+        Level::parse(contents, Path::new(""), &prefabs, &HashMap::new())
+    }
+
+    #[test]
+    fn levels_need_exactly_one_player() {
+        assert!(matches!(parse(""), Err(LevelError::PlayerCount(0))));
+        assert!(matches!(
+            parse(&PLAYER.repeat(2)),
+            Err(LevelError::PlayerCount(2))
+        ));
+        assert!(parse(PLAYER).is_ok());
+    }
+
+    #[test]
+    fn collision_masks_are_found_next_to_the_level() {
+        let directory =
+            std::env::temp_dir().join(format!("a_little_guy_{}_masked_level", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        image::RgbaImage::from_pixel(2, 2, image::Rgba([0, 0, 0, 255]))
+            .save(directory.join("mask.png"))
+            .unwrap();
+        let level_path = directory.join("level.toml");
+        std::fs::write(
+            &level_path,
+            format!("collision_mask = {{ path = \"mask.png\" }}\n{}", PLAYER),
+        )
+        .unwrap();
+        let prefabs = Prefabs::load("assets/prefabs.toml", &collision_meshes().1).unwrap();
+        let result = Level::load(&level_path, &prefabs, &HashMap::new());
+        std::fs::remove_dir_all(directory).unwrap();
+        assert!(result
+            .unwrap()
+            .world_collision_mesh
+            .surrounding_aabb()
+            .is_some());
+    }
+
+    #[test]
+    fn entities_with_bad_components_are_reported_by_index() {
+        let level = format!("{}[[entities]]\nwings = {{}}\n", PLAYER);
+        match parse(&level) {
+            Err(LevelError::Entity {
+                entity: 1,
+                error: EntityError::UnknownComponent(component),
+            }) => assert_eq!(component, "wings"),
+            _ => panic!("the unknown component was accepted"),
+        }
+
+        let level = format!("{}[[entities]]\nplayer = \"yes\"\n", PLAYER);
+        assert!(matches!(
+            parse(&level),
+            Err(LevelError::Entity { entity: 1, .. })
+        ));
+    }
+
+    #[test]
+    fn collision_polygons_are_placed_with_the_image_transform() {
+        let level = format!(
+            "image_transform = {{ origin = [10.0, 10.0], scale = 2.0 }}\n\
+             {}\
+             [[collision_polygons]]\n\
+             outline = [[10.0, 10.0], [20.0, 10.0], [20.0, 20.0], [10.0, 20.0]]\n",
+            PLAYER
+        );
+        let level = parse(&level).unwrap();
+        // Pixels below the origin are below it in the world too, where y points up
+        let aabb = level.world_collision_mesh.surrounding_aabb().unwrap();
+        assert_eq!(
+            (aabb.min_x, aabb.min_y, aabb.max_x, aabb.max_y),
+            (0.0, -20.0, 20.0, 0.0)
+        );
+
+        let level = format!(
+            "{}[[collision_polygons]]\noutline = [[0.0, 0.0], [1.0, 0.0]]\n",
+            PLAYER
+        );
+        assert!(matches!(parse(&level), Err(LevelError::Polygon(0))));
+    }
+
+    #[test]
+    fn solver_iterations_default_to_4() {
+        assert_eq!(parse(PLAYER).unwrap().solver_iterations, 4);
+        let level = format!("solver_iterations = 10\n{}", PLAYER);
+        assert_eq!(parse(&level).unwrap().solver_iterations, 10);
+    }
+
+    #[test]
+    fn camera_settings_out_of_range_are_rejected() {
+        let prefabs = Prefabs::load("assets/prefabs.toml", &collision_meshes().1).unwrap();
+        for (camera, expected) in [
+            ("dead_zone = [96.0, -1.0]", "dead_zone"),
+            ("smoothing_seconds = -0.5", "smoothing_seconds"),
+            ("look_ahead_seconds = -0.25", "look_ahead_seconds"),
+            ("max_look_ahead = -10.0", "max_look_ahead"),
+            ("zoom = 0.0", "zoom"),
+        ] {
+            let level = format!("camera = {{ {} }}", camera);
+            match Level::parse(&level, Path::new(""), &prefabs, &HashMap::new()) {
+                Err(LevelError::CameraSetting { setting, .. }) => assert_eq!(setting, expected),
+                _ => panic!("`{}` was accepted", camera),
+            }
+        }
+    }
+}
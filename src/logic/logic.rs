@@ -1,487 +1,265 @@
 use std::{
     collections::HashMap,
+    path::PathBuf,
+    sync::mpsc,
     thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 
-use glm::Vec2;
 use legion::*;
-use rand::{
-    prelude::{StdRng, ThreadRng},
-    Rng, SeedableRng,
-};
+use rand::{prelude::ThreadRng, Rng};
 
 use super::{
-    collision::{Aabb, CollisionMesh, CollisionMeshIdentifier, Triangle},
+    external_event_handler::ExternalEventHandler,
+    game::Game,
+    replay::{Replay, ReplayRecorder},
+    session_event::SessionEvent,
+    state_input_event::StateInputEvent,
     *,
 };
 use crate::{
     channels::{LogicToWindowSender, WindowToLogicReceiver},
-    graphics::DrawState,
-    logic::{Collider, TimedLife},
+    graphics::{DrawState, ExternalEvent},
 };
 
-use super::state_input_event::*;
-
-use std::time::SystemTime;
+/// Where the game is saved to and loaded from.
+const SAVE_PATH: &str = "saves/quicksave.toml";
 
-pub fn setup_world(
-    collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
-) -> (World, Entity) {
-    println!("Hello, world!");
+/// How many ticks the game is simulated with per second, unless configured otherwise.
+pub const DEFAULT_TICKS_PER_SECOND: f32 = 30.0;
 
-    let mut world = World::default();
+/// How many ticks the logic thread may catch up on at once before it gives up on keeping pace with real time.
+const MAX_CATCH_UP_TICKS: u32 = 5;
 
-    let player = world.push((
-        Position { x: 0.0, y: 0.0 },
-        Velocity { dx: 0.0, dy: 0.0 },
-        Asset {
-            name: "player".into(),
-            animation: 0,
-            animation_start_time: 0.0,
-        },
-        Friction {},
-        Collider {
-            collision_mesh: collision_mesh_identifiers["basic"],
-            size: 96.0,
-        },
-        Status {
-            collides_with_own_team: true,
-            team: Team::PLAYER,
-        },
-    ));
+/// How the logic thread should run the game.
+pub struct LogicConfig {
+    pub level_path: PathBuf,
+    pub ticks_per_second: f32,
+    /// Records the input events of every tick to this file, so the game can be replayed.
+    pub record_path: Option<PathBuf>,
+    /// Plays back the input events recorded in this file instead of the player's input,
+    /// until the recording runs out.
+    pub replay_path: Option<PathBuf>,
+}
 
-    world.push(
-        // The background
-        (
-            Position { x: 0.0, y: 0.0 },
-            Asset {
-                name: "background".into(),
-                animation: 0,
-                animation_start_time: 0.0,
-            },
-        ),
-    );
+///
+/// Turns external events into the input events of each tick,
+/// taking replaying, recording, saving and loading into account.
+///
+pub struct InputPipeline {
+    evh: ExternalEventHandler,
+    replay: Option<Replay>,
+    recorder: Option<ReplayRecorder>,
+    tick: usize,
+}
 
-    world.push(
-        // A bush
-        (
-            Position { x: 100.0, y: 100.0 },
-            Asset {
-                name: "bush".into(),
-                animation: 0,
-                animation_start_time: 0.0,
-            },
-            Collider {
-                collision_mesh: collision_mesh_identifiers["basic"],
-                size: 16.0,
+impl InputPipeline {
+    ///
+    /// Sets up the game and its input as configured.
+    /// Returns them together with the length of a tick.
+    ///
+    pub fn start(config: &LogicConfig) -> (Game, InputPipeline, Duration) {
+        let replay = config.replay_path.as_ref().map(|path| {
+            let replay = Replay::load(path).unwrap_or_else(|error| {
+                panic!("Could not load replay {}: {}", path.display(), error)
+            });
+            println!(
+                "Replaying {} ticks from {}",
+                replay.history.cur_tick_num(),
+                path.display()
+            );
+            replay
+        });
+        // The random number generator must be seeded the same way, and the game must tick at the same rate,
+        // for a replay to reproduce the game.
+        let (seed, ticks_per_second) = match &replay {
+            Some(replay) => (replay.seed, replay.ticks_per_second),
+            None => (rand::random(), config.ticks_per_second),
+        };
+        let recorder = config.record_path.as_ref().map(|path| {
+            ReplayRecorder::create(path, seed, ticks_per_second).unwrap_or_else(|error| {
+                panic!("Could not record replay {}: {}", path.display(), error)
+            })
+        });
+
+        let tick_duration = Duration::from_secs_f32(1.0 / ticks_per_second);
+        let game = Game::new(&config.level_path, seed, tick_duration.as_secs_f32()).unwrap_or_else(
+            |error| {
+                panic!(
+                    "Could not load level {}: {}",
+                    config.level_path.display(),
+                    error
+                )
             },
-        ),
-    );
+        );
 
-    world.push(
-        // A lamp post
-        (
-            Position {
-                x: 480.0,
-                y: -540.0,
-            },
-            Asset {
-                name: "lamp post".into(),
-                animation: 0,
-                animation_start_time: 0.0,
-            },
-            Collider {
-                collision_mesh: collision_mesh_identifiers["basic"],
-                size: 32.0,
-            },
-        ),
-    );
+        let input = InputPipeline {
+            evh: ExternalEventHandler::new(controls::ControlConfig::default()),
+            replay,
+            recorder,
+            tick: 0,
+        };
+        (game, input, tick_duration)
+    }
 
-    for _ in 0..5 {
+    /// Empties the channel of new external events and handles any saving or loading they ask for.
+    pub fn handle_inputs(&mut self, receiver: &mpsc::Receiver<ExternalEvent>, game: &mut Game) {
+        self.evh.handle_inputs(receiver);
 
-        world.push(
-            // A firefly
-            (
-                Position { x: 0.0, y: 0.0 },
-                Velocity { dx: 0.0, dy: 0.0 },
-                Friction {},
-                Asset {
-                    name: "firefly".into(),
-                    animation: 0,
-                    animation_start_time: 0.0,
+        for session_event in self.evh.tick_session_events() {
+            match session_event {
+                // Loads aren't in the replay, so a replay with one would play out differently
+                SessionEvent::Load if self.replay.is_some() || self.recorder.is_some() => {
+                    println!("Loading is turned off while recording or replaying.")
+                }
+                SessionEvent::Save => match game.save(SAVE_PATH) {
+                    Ok(()) => println!("Saved the game to {}", SAVE_PATH),
+                    Err(error) => println!("Could not save the game: {}", error),
                 },
-                AiRandomWalk { speed: 192.0, center : glm::vec2(0.0,0.0), centering_speed : 0.01 },
-            ),
-        );
+                SessionEvent::Load => match game.load(SAVE_PATH) {
+                    Ok(()) => println!("Loaded the game from {}", SAVE_PATH),
+                    Err(error) => println!("Could not load the game: {}", error),
+                },
+            }
+        }
     }
-    world.push(
-        // A firefly
-        (
-            Position { x: 0.0, y: 0.0 },
-            Velocity { dx: 0.0, dy: 0.0 },
-            Friction {},
-            Asset {
-                name: "firefly".into(),
-                animation: 0,
-                animation_start_time: 0.0,
-            },
-            AiRandomWalk { speed: 1920.0, center : glm::vec2(0.0,0.0), centering_speed : 0.01  },
-        ),
-    );
-    world.push(
-        // A firefly
-        (
-            Position { x: 0.0, y: 0.0 },
-            Velocity { dx: 0.0, dy: 0.0 },
-            Friction {},
-            Asset {
-                name: "firefly".into(),
-                animation: 0,
-                animation_start_time: 0.0,
-            },
-            AiRandomWalk { speed: 1920.0, center : glm::vec2(0.0,0.0), centering_speed : 0.001  },
-        ),
-    );
-    world.push(
-        // A firefly
-        (
-            Position { x: 0.0, y: 0.0 },
-            Velocity { dx: 0.0, dy: 0.0 },
-            Friction {},
-            Asset {
-                name: "firefly".into(),
-                animation: 0,
-                animation_start_time: 0.0,
-            },
-            AiRandomWalk { speed: 1920.0, center : glm::vec2(0.0,0.0), centering_speed : 0.00001  },
-        ),
-    );
-
-    (world, player)
-}
 
-pub fn setup_schedule() -> Schedule {
-    Schedule::builder()
-        .add_system(update_positions_system())
-        .add_system(update_velocities_system())
-        .add_system(update_lives_system())
-        .add_system(random_walk_ai_system())
-        .build()
-}
-
-pub fn setup_resources() -> Resources {
-    let mut resources = Resources::default();
-    resources.insert(Time {
-        elapsed_seconds: 0.0f32,
-    });
-    resources.insert(StdRng::from_entropy());
-
-    resources
-}
-
-pub fn step(world: &mut World, schedule: &mut Schedule, resources: &mut Resources) {
-    resources.insert(Time {
-        elapsed_seconds: 0.033,
-    });
-    schedule.execute(world, resources)
+    /// Returns the input events for the next tick.
+    pub fn tick_events(&mut self) -> Vec<StateInputEvent> {
+        let mut events = self.evh.tick_events();
+        if let Some(replay) = &self.replay {
+            match replay.history.get_events(self.tick) {
+                Some(replay_events) => events = replay_events.to_vec(),
+                None => {
+                    println!("The replay has finished.");
+                    self.replay = None;
+                }
+            }
+        }
+        if let Some(recorder) = &mut self.recorder {
+            if let Err(error) = recorder.record_tick(&events) {
+                println!("Could not record tick {}: {}", self.tick, error);
+            }
+        }
+        self.tick += 1;
+        events
+    }
 }
 
-#[derive(Default)]
 ///
-/// TODO: BAD NAME
-/// All the stuff around the player, like if they're looking somewhere or if the camera's shaking or whatever.
-struct ExtraInfo {
-    shake: f32,
-    speed: f32,
-    charge: u32,
+/// What was drawn in a tick, kept so the next tick can be drawn interpolated from it.
+///
+struct TickSnapshot {
+    positions: HashMap<Entity, Position>,
+    camera: Position,
+    time: f32,
 }
 
-impl ExtraInfo {
-    pub fn new() -> Self {
+impl TickSnapshot {
+    fn capture(game: &Game) -> Self {
+        let world = game.world();
         Self {
-            shake: 0.0,
-            speed: 16.0,
-            ..Default::default()
+            positions: <(Entity, &Position)>::query()
+                .iter(world)
+                .map(|(entity, position)| (*entity, *position))
+                .collect(),
+            camera: game.camera().position().into(),
+            time: game.game_time(),
         }
     }
-    pub fn update(&mut self) {
-        self.shake *= 0.98;
-    }
-}
-
-fn handle_timed_life(world: &mut World) {
-    let mut q = <(Entity, &TimedLife)>::query();
-    let removed_entities: Vec<Entity> = q
-        .iter(world)
-        .flat_map(|(entity, time)| {
-            if time.seconds_left <= 0.0 {
-                Some(*entity)
-            } else {
-                None
-            }
-        })
-        .collect();
-
-    for entity in removed_entities {
-        world.remove(entity);
-    }
 }
 
 fn create_draw_state(
-    world: &World,
-    extra_info: &ExtraInfo,
-    position: &Position,
+    game: &Game,
+    previous: &TickSnapshot,
     rng: &mut ThreadRng,
-    first_time: &SystemTime,
+    tick_instant: Instant,
+    tick_duration: Duration,
 ) -> DrawState {
-    let mut drawing_query = <(&Asset, &Position)>::query();
-    let draw_positions: Vec<(Asset, Position)> = drawing_query
-        .iter(world)
-        .map(|(asset, position)| {
+    let current = TickSnapshot::capture(game);
+    let mut drawing_query = <(Entity, &Asset, &Position)>::query();
+    let draw_positions: Vec<(Asset, Position, Position)> = drawing_query
+        .iter(game.world())
+        .map(|(entity, asset, position)| {
             (
                 asset.clone(),
-                Position {
-                    x: position.x.floor(),
-                    y: position.y.floor(),
-                },
+                *previous.positions.get(entity).unwrap_or(position),
+                *position,
             )
         })
         .collect();
+    let shake = game.extra_info().shake;
+    let shake = [
+        rng.gen_range(-1.0..1.0) * shake,
+        rng.gen_range(-1.0..1.0) * shake,
+    ];
     DrawState::new(
         draw_positions,
         [
-            rng.gen_range(-1.0..1.0) * extra_info.shake - position.x,
-            rng.gen_range(-1.0..1.0) * extra_info.shake - position.y,
+            [previous.camera.x, previous.camera.y],
+            [current.camera.x, current.camera.y],
         ],
-        first_time.elapsed().unwrap().as_secs_f32(),
+        shake,
+        game.camera().view(),
+        [previous.time, current.time],
+        tick_instant,
+        tick_duration,
     )
 }
 
-pub fn start_logic_thread(rx: WindowToLogicReceiver, tx: LogicToWindowSender) -> JoinHandle<()> {
+pub fn start_logic_thread(
+    config: LogicConfig,
+    rx: WindowToLogicReceiver,
+    tx: LogicToWindowSender,
+) -> JoinHandle<()> {
     thread::spawn(move || {
         let event_receiver = rx.channel_receiver;
         let graphics_sender = tx.render_pack;
 
-        let (collision_mesh_manager, collision_mesh_identifiers) = {
-            let mut collision_mesh_manager = super::collision::CollisionMeshManager::new();
-            let mut collision_mesh_identifiers: HashMap<String, CollisionMeshIdentifier> =
-                HashMap::new();
-
-            let basic_identifier =
-                collision_mesh_manager.add_collision_mesh(CollisionMesh::new(Aabb {
-                    min_x: -0.5,
-                    min_y: -0.5,
-                    max_x: 0.5,
-                    max_y: 0.5,
-                }));
-            collision_mesh_identifiers.insert("basic".into(), basic_identifier);
-
-            (collision_mesh_manager, collision_mesh_identifiers)
-        };
-
-        let (mut world, player) = setup_world(&collision_mesh_identifiers);
-        let mut schedule = setup_schedule();
-        let mut resources = setup_resources();
-
-        let mut extra_info = ExtraInfo::new();
-
-        let mut evh =
-            external_event_handler::ExternalEventHandler::new(controls::ControlConfig::default());
+        let (mut game, mut input, tick_duration) = InputPipeline::start(&config);
 
         let mut rng = rand::thread_rng();
 
-        let first_time = SystemTime::now();
+        let mut previous_snapshot = TickSnapshot::capture(&game);
 
-        let mut start_time = SystemTime::now();
-
-        let test_triangles = vec![
-            Triangle::new(
-                glm::vec2((702.0-800.0)*3.0, -(753.0-800.0)*3.0),
-                glm::vec2((765.0-800.0)*3.0, -(762.0-800.0)*3.0),
-                glm::vec2((711.0-800.0)*3.0, -(723.0-800.0)*3.0),
-            ),
-            Triangle::new(
-                glm::vec2((770.0-800.0)*3.0, -(691.0-800.0)*3.0),
-                glm::vec2((765.0-800.0)*3.0, -(762.0-800.0)*3.0),
-                glm::vec2((711.0-800.0)*3.0, -(723.0-800.0)*3.0),
-            ),
-
-        ];
-        println!("{:?}", test_triangles);
-
-        let world_collision_mesh = collision::WorldCollisionMesh::new(test_triangles);
+        // Real time that has passed but has not been simulated yet.
+        let mut lag = Duration::ZERO;
+        let mut last_instant = Instant::now();
 
         loop {
-            resources.insert(Time {
-                elapsed_seconds: start_time.elapsed().unwrap().as_secs_f32(),
-            });
-            start_time = SystemTime::now();
-
-            evh.handle_inputs(&event_receiver);
-            let events = evh.tick_events();
-
-            let (mut velocity, position) = if let Some(player_entry) = world.entry(player) {
-                (
-                    *player_entry.get_component::<Velocity>().unwrap(),
-                    *player_entry.get_component::<Position>().unwrap(),
-                )
-            } else {
-                panic!("The player has disappeared!");
-            };
-
-            {
-                // HANDLE INPUT EVENTS
-                for event in events {
-                    match event {
-                        StateInputEvent::MovePlayerRelative { delta } => {
-                            velocity.dx += delta.x * extra_info.speed;
-                            velocity.dy += delta.y * extra_info.speed;
-                        }
-                        StateInputEvent::Jump => extra_info.shake += 5.0,
-                        StateInputEvent::Charge(_) => {
-                            extra_info.speed = 2.0;
-                            if extra_info.charge < 30 {
-                                extra_info.charge += 1;
-                            }
-                        }
-                        StateInputEvent::Shoot(direction) => {
-                            extra_info.speed = 16.0;
-                            if extra_info.charge > 10 {
-                                world.push((
-                                    Asset {
-                                        name: "arrow".into(),
-                                        animation: 0,
-                                        animation_start_time: first_time
-                                            .elapsed()
-                                            .unwrap()
-                                            .as_secs_f32(),
-                                    },
-                                    position,
-                                    Velocity::from(
-                                        Vec2::from(direction) * (32.0 * (extra_info.charge as f32)),
-                                    ),
-                                    TimedLife { seconds_left: 1.0 },
-                                    Collider {
-                                        collision_mesh: collision_mesh_identifiers["basic"],
-                                        size: 48.0,
-                                    },
-                                    Status {
-                                        collides_with_own_team: false,
-                                        team: Team::PLAYER,
-                                    },
-                                ));
-                            }
-                            extra_info.charge = 0;
-                        }
-                    }
-                }
+            let now = Instant::now();
+            lag += now - last_instant;
+            last_instant = now;
+            if lag > tick_duration * MAX_CATCH_UP_TICKS {
+                println!("The game is running behind, skipping {:?}", lag);
+                lag = tick_duration * MAX_CATCH_UP_TICKS;
             }
 
-            if let Some(mut player_entry) = world.entry(player) {
-                *player_entry.get_component_mut::<Velocity>().unwrap() = velocity;
-                *player_entry.get_component_mut::<Position>().unwrap() = position;
-            } else {
-                panic!("The player has disappeared!");
-            };
+            input.handle_inputs(&event_receiver, &mut game);
 
-            // Do world step
-            step(&mut world, &mut schedule, &mut resources);
+            let mut ticked = false;
+            while lag >= tick_duration {
+                lag -= tick_duration;
 
-            // Remove entities whose lives are over
-            handle_timed_life(&mut world);
-
-            //TODO: COLLISION
-            let mut colliding_entities: Vec<(Entity, Option<Entity>, Vec2)> = Vec::new();
-            let mut collision_query_1 = <(&Position, &Collider, Entity)>::query();
-            let mut collision_query_2 = <(&Position, &Collider, Entity)>::query();
-            for (position_1, collider_1, entity_1) in collision_query_1.iter(&world) {
-                let collision_mesh_1 = collision_mesh_manager.get_collision_mesh(
-                    collider_1.collision_mesh,
-                    Vec2::from(*position_1),
-                    collider_1.size,
-                );
-                for (position_2, collider_2, entity_2) in collision_query_2.iter(&world) {
-                    if entity_1 != entity_2 {
-                        let collision_mesh_2 = collision_mesh_manager.get_collision_mesh(
-                            collider_2.collision_mesh,
-                            Vec2::from(*position_2),
-                            collider_2.size,
-                        );
-                        if collision_mesh_1.is_colliding(&collision_mesh_2) {
-                            colliding_entities.push((
-                                *entity_1,
-                                Some(*entity_2),
-                                collision_mesh_1.closest_intersection_vector(&collision_mesh_2),
-                            ));
-                        }
-                    }
-                }
-                if let Some(closest_intersection_vector) = world_collision_mesh.find_collision(&collision_mesh_1.aabb) {
-                    colliding_entities.push((
-                        *entity_1,
-                        None,
-                        closest_intersection_vector,
-                    ));
-                }
+                let events = input.tick_events();
+                previous_snapshot = TickSnapshot::capture(&game);
+                game.tick(events);
+                ticked = true;
             }
 
-            for (ent1, ent2, collision_vector) in colliding_entities {
-                if let Some(ent2) = ent2 {
-                    // Check status; if these entities are on the same team and either doesn't collide with their own team, they shouldn't collide.
-                    let ent1entry = world.entry_ref(ent1).unwrap();
-                    let ent2entry = world.entry_ref(ent2).unwrap();
-                    if let (Ok(status1), Ok(status2)) = (
-                        ent1entry.get_component::<Status>(),
-                        ent2entry.get_component::<Status>(),
-                    ) {
-                        if status1.team == status2.team
-                            && (!status1.collides_with_own_team || !status2.collides_with_own_team)
-                        {
-                            continue;
-                        }
-                    }
-                }
-
-                let mut ent1entry = world.entry(ent1).unwrap();
-                println!("Found entry! {:?}, {:?}", ent1, ent2);
-
-                if let Ok(_) = ent1entry.get_component::<Velocity>() {
-                    println!("Collision vector: {:?}", collision_vector);
-                    // Only move entities that have velocities === can move
-                    {
-                        let position = ent1entry.get_component_mut::<Position>().unwrap();
-                        position.x += collision_vector.x;
-                        position.y += collision_vector.y;
-                    }
-                    if collision_vector.dot(&collision_vector) > 0.0001 {
-                        // We can only normalize if it's a non-zero vector
-                        let velocity = ent1entry.get_component_mut::<Velocity>().unwrap();
-                        let dvel = Vec2::from(*velocity).dot(&collision_vector.normalize())
-                            * collision_vector.normalize();
-                        velocity.dx -= dvel.x;
-                        velocity.dy -= dvel.y;
-                    }
-                }
+            if ticked {
+                // The latest tick corresponds to this moment in real time, which the window interpolates from.
+                let tick_instant = now - lag;
+                let _ = graphics_sender.send(create_draw_state(
+                    &game,
+                    &previous_snapshot,
+                    &mut rng,
+                    tick_instant,
+                    tick_duration,
+                ));
             }
 
-            extra_info.update();
-            let _ = graphics_sender.send(create_draw_state(
-                &world,
-                &extra_info,
-                &position,
-                &mut rng,
-                &first_time,
-            ));
-
-            let end_time = SystemTime::now();
-            let tick_duration = end_time.duration_since(start_time).unwrap().as_millis();
-            if tick_duration < 33 {
-                std::thread::sleep(std::time::Duration::from_millis(
-                    (33 - tick_duration) as u64,
-                ))
-            } else {
-                println!("Tick took longer than 33ms!");
-            }
+            thread::sleep(tick_duration - lag);
         }
     })
 }
@@ -3,19 +3,42 @@ pub use components::*;
 
 mod resources;
 use glm::Vec2;
-pub use resources::Time;
+pub use resources::{
+    CollisionEvents, Contact, ContactPhase, PlayerPosition, Shot, Shots, StateChange, StateChanges,
+    Time,
+};
 
 mod systems;
 pub use systems::{
-    random_walk_ai_system, update_lives_system, update_positions_system, update_velocities_system,
+    apply_damage_system, chase_ai_system, collect_pickups_system, flee_ai_system,
+    follow_path_system, keep_distance_ai_system, patrol_ai_system, random_walk_ai_system,
+    shoot_ai_system, state_machine_ai_system, steering_ai_system, update_invulnerability_system,
+    update_lives_system, update_positions_system, update_velocities_system,
 };
 
+mod camera;
+pub use camera::CameraView;
+
+#[cfg(test)]
+mod benchmark;
+
 mod controls;
 mod external_event_handler;
+mod game;
+mod headless;
+mod level;
+mod navigation;
+mod prefab;
+mod replay;
+mod save_game;
+mod session_event;
 mod state_input_event;
+mod steering;
 
+#[allow(clippy::module_inception)]
 mod logic;
-pub use logic::start_logic_thread;
+pub use headless::{run_headless, HeadlessConfig};
+pub use logic::{start_logic_thread, LogicConfig, DEFAULT_TICKS_PER_SECOND};
 
 use serde::{Deserialize, Serialize};
 
@@ -0,0 +1,361 @@
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+use glm::Vec2;
+
+use super::collision::Aabb;
+
+/// Offsets to the neighbours of a cell, orthogonal ones first.
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (0, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+    (1, -1),
+];
+
+/// A cell waiting to be searched, ordered so the one with the lowest cost comes out of a `BinaryHeap` first.
+struct OpenCell {
+    cost: f32,
+    cell: usize,
+}
+
+impl PartialEq for OpenCell {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenCell {}
+
+impl PartialOrd for OpenCell {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for OpenCell {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| other.cell.cmp(&self.cell))
+    }
+}
+
+///
+/// A grid of square cells over the level, marking which are blocked by obstacles,
+/// for finding paths around them with A*.
+///
+/// Each cell also knows how far it is from the nearest blocked cell,
+/// so agents only walk through cells where they fit.
+///
+pub struct NavigationGrid {
+    /// Where the corner of the first cell is, at the lowest x and y.
+    origin: Vec2,
+    cell_size: f32,
+    columns: usize,
+    rows: usize,
+    blocked: Vec<bool>,
+    /// Distance from the center of each cell to the nearest blocked cell's edge.
+    clearance: Vec<f32>,
+}
+
+impl NavigationGrid {
+    ///
+    /// Covers the bounds with cells of the size, blocking those whose area `is_blocked` returns true for.
+    ///
+    pub fn new(bounds: &Aabb, cell_size: f32, is_blocked: impl Fn(&Aabb) -> bool) -> Self {
+        let columns = ((bounds.max_x - bounds.min_x) / cell_size).ceil().max(1.0) as usize;
+        let rows = ((bounds.max_y - bounds.min_y) / cell_size).ceil().max(1.0) as usize;
+        let mut grid = NavigationGrid {
+            origin: glm::vec2(bounds.min_x, bounds.min_y),
+            cell_size,
+            columns,
+            rows,
+            blocked: Vec::with_capacity(columns * rows),
+            clearance: Vec::new(),
+        };
+        for cell in 0..columns * rows {
+            let blocked = is_blocked(&grid.cell_aabb(cell));
+            grid.blocked.push(blocked);
+        }
+        grid.clearance = grid.measure_clearance();
+        grid
+    }
+
+    fn cell_aabb(&self, cell: usize) -> Aabb {
+        let corner = self.origin
+            + glm::vec2((cell % self.columns) as f32, (cell / self.columns) as f32)
+                * self.cell_size;
+        Aabb {
+            min_x: corner.x,
+            min_y: corner.y,
+            max_x: corner.x + self.cell_size,
+            max_y: corner.y + self.cell_size,
+        }
+    }
+
+    fn cell_center(&self, cell: usize) -> Vec2 {
+        self.origin
+            + (glm::vec2((cell % self.columns) as f32, (cell / self.columns) as f32)
+                + glm::vec2(0.5, 0.5))
+                * self.cell_size
+    }
+
+    fn cell_at(&self, point: Vec2) -> Option<usize> {
+        let offset = (point - self.origin) / self.cell_size;
+        if offset.x < 0.0 || offset.y < 0.0 {
+            return None;
+        }
+        let (column, row) = (offset.x as usize, offset.y as usize);
+        if column < self.columns && row < self.rows {
+            Some(row * self.columns + column)
+        } else {
+            None
+        }
+    }
+
+    fn neighbour(&self, cell: usize, (dx, dy): (i32, i32)) -> Option<usize> {
+        let column = (cell % self.columns) as i32 + dx;
+        let row = (cell / self.columns) as i32 + dy;
+        if column < 0 || row < 0 || column >= self.columns as i32 || row >= self.rows as i32 {
+            None
+        } else {
+            Some(row as usize * self.columns + column as usize)
+        }
+    }
+
+    fn step_length(&self, (dx, dy): (i32, i32)) -> f32 {
+        if dx != 0 && dy != 0 {
+            self.cell_size * std::f32::consts::SQRT_2
+        } else {
+            self.cell_size
+        }
+    }
+
+    /// Spreads out from all the blocked cells at once, finding how far every other cell is from them.
+    fn measure_clearance(&self) -> Vec<f32> {
+        let mut distances = vec![f32::INFINITY; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        for (cell, blocked) in self.blocked.iter().enumerate() {
+            if *blocked {
+                distances[cell] = 0.0;
+                open.push(OpenCell { cost: 0.0, cell });
+            }
+        }
+        while let Some(OpenCell { cost, cell }) = open.pop() {
+            if cost > distances[cell] {
+                continue;
+            }
+            for offset in NEIGHBOURS {
+                if let Some(neighbour) = self.neighbour(cell, offset) {
+                    let distance = cost + self.step_length(offset);
+                    if distance < distances[neighbour] {
+                        distances[neighbour] = distance;
+                        open.push(OpenCell {
+                            cost: distance,
+                            cell: neighbour,
+                        });
+                    }
+                }
+            }
+        }
+        distances
+            .into_iter()
+            .map(|distance| distance - self.cell_size / 2.0)
+            .collect()
+    }
+
+    fn is_walkable(&self, cell: usize, radius: f32) -> bool {
+        !self.blocked[cell] && self.clearance[cell] >= radius
+    }
+
+    /// Whether an agent of the radius can walk in a straight line between the points.
+    fn is_clear(&self, start: Vec2, end: Vec2, radius: f32) -> bool {
+        let steps = ((end - start).norm() / (self.cell_size / 2.0)).ceil() as usize;
+        (0..=steps).all(|step| {
+            let point = start + (end - start) * (step as f32 / steps.max(1) as f32);
+            self.cell_at(point)
+                .is_some_and(|cell| self.is_walkable(cell, radius))
+        })
+    }
+
+    ///
+    /// Finds a path for an agent of the radius from the start to the goal, around blocked cells,
+    /// as the points to walk to in turn, ending with the goal.
+    ///
+    /// Returns `None` if the goal is off the grid or the agent can't get there.
+    /// An agent starting where it doesn't fit can still walk out to where it does.
+    ///
+    pub fn find_path(&self, start: Vec2, goal: Vec2, radius: f32) -> Option<Vec<Vec2>> {
+        let start_cell = self.cell_at(start)?;
+        let goal_cell = self.cell_at(goal)?;
+        if !self.is_walkable(goal_cell, radius) {
+            return None;
+        }
+
+        // Octile distance, which never overestimates on a grid with diagonal steps
+        let heuristic = |cell: usize| {
+            let offset = self.cell_center(cell) - self.cell_center(goal_cell);
+            let (long, short) = (
+                offset.x.abs().max(offset.y.abs()),
+                offset.x.abs().min(offset.y.abs()),
+            );
+            long + (std::f32::consts::SQRT_2 - 1.0) * short
+        };
+        let mut costs = vec![f32::INFINITY; self.blocked.len()];
+        let mut came_from = vec![usize::MAX; self.blocked.len()];
+        let mut open = BinaryHeap::new();
+        costs[start_cell] = 0.0;
+        open.push(OpenCell {
+            cost: heuristic(start_cell),
+            cell: start_cell,
+        });
+        while let Some(OpenCell { cost, cell }) = open.pop() {
+            if cell == goal_cell {
+                break;
+            }
+            if cost > costs[cell] + heuristic(cell) {
+                continue;
+            }
+            for offset in NEIGHBOURS {
+                let neighbour = match self.neighbour(cell, offset) {
+                    Some(neighbour) if self.is_walkable(neighbour, radius) => neighbour,
+                    _ => continue,
+                };
+                // Diagonal steps can't cut the corners of cells the agent doesn't fit in
+                if offset.0 != 0 && offset.1 != 0 {
+                    let sides = [(offset.0, 0), (0, offset.1)];
+                    if !sides.iter().all(|side| {
+                        self.neighbour(cell, *side)
+                            .is_some_and(|side| self.is_walkable(side, radius))
+                    }) {
+                        continue;
+                    }
+                }
+                let neighbour_cost = costs[cell] + self.step_length(offset);
+                if neighbour_cost < costs[neighbour] {
+                    costs[neighbour] = neighbour_cost;
+                    came_from[neighbour] = cell;
+                    open.push(OpenCell {
+                        cost: neighbour_cost + heuristic(neighbour),
+                        cell: neighbour,
+                    });
+                }
+            }
+        }
+        if costs[goal_cell].is_infinite() {
+            return None;
+        }
+
+        let mut cells = vec![goal_cell];
+        while let Some(cell) = cells.last().map(|cell| came_from[*cell]) {
+            if cell == usize::MAX {
+                break;
+            }
+            cells.push(cell);
+        }
+        cells.pop();
+        cells.reverse();
+        let mut points: Vec<Vec2> = cells.iter().map(|cell| self.cell_center(*cell)).collect();
+        if let Some(last) = points.last_mut() {
+            *last = goal;
+        } else {
+            points.push(goal);
+        }
+
+        // Skip the points that can be cut straight past
+        let mut path = Vec::new();
+        let mut from = start;
+        let mut index = 0;
+        while index < points.len() {
+            let furthest = (index + 1..points.len())
+                .rev()
+                .find(|later| self.is_clear(from, points[*later], radius))
+                .unwrap_or(index);
+            from = points[furthest];
+            path.push(from);
+            index = furthest + 1;
+        }
+        Some(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::collision::{Triangle, WorldCollisionMesh};
+    use super::*;
+
+    /// A grid of 10 by 10 cells of size 1, blocking the cells that `is_blocked` returns true for by column and row.
+    fn grid(is_blocked: impl Fn(usize, usize) -> bool) -> NavigationGrid {
+        let bounds = Aabb {
+            min_x: 0.0,
+            min_y: 0.0,
+            max_x: 10.0,
+            max_y: 10.0,
+        };
+        NavigationGrid::new(&bounds, 1.0, |cell| {
+            is_blocked(cell.min_x as usize, cell.min_y as usize)
+        })
+    }
+
+    #[test]
+    fn agents_only_walk_where_they_fit() {
+        // A wall down the middle with a gap one cell wide
+        let grid = grid(|column, row| column == 5 && row != 5);
+        let (start, goal) = (glm::vec2(1.5, 1.5), glm::vec2(8.5, 1.5));
+
+        let path = grid.find_path(start, goal, 0.4).unwrap();
+        assert_eq!(path.last(), Some(&goal));
+        let mut from = start;
+        for point in path.iter() {
+            assert!(grid.is_clear(from, *point, 0.4));
+            from = *point;
+        }
+        assert!(path.iter().any(|point| (point.y - 5.5).abs() < 0.001));
+
+        assert!(grid.find_path(start, goal, 0.6).is_none());
+        // Agents can't walk to where they don't fit, but can walk out from there
+        assert!(grid.find_path(start, glm::vec2(5.5, 5.5), 0.6).is_none());
+        assert!(grid.find_path(glm::vec2(5.5, 5.5), goal, 0.6).is_some());
+    }
+
+    #[test]
+    fn diagonal_steps_dont_cut_corners() {
+        // A wall of cells only touching at their corners
+        let grid = grid(|column, row| column + row == 9);
+        assert!(grid
+            .find_path(glm::vec2(2.5, 2.5), glm::vec2(7.5, 7.5), 0.0)
+            .is_none());
+        assert!(grid
+            .find_path(glm::vec2(2.5, 2.5), glm::vec2(1.5, 6.5), 0.0)
+            .is_some());
+    }
+
+    #[test]
+    fn cells_inside_world_triangles_are_blocked() {
+        // Much larger than a cell, so most of the cells it covers don't touch its sides
+        let world_collision_mesh = WorldCollisionMesh::new(vec![Triangle::new(
+            glm::vec2(2.0, -1.0),
+            glm::vec2(8.0, -1.0),
+            glm::vec2(5.0, 8.0),
+        )]);
+        let bounds = Aabb {
+            min_x: 0.0,
+            min_y: 0.0,
+            max_x: 10.0,
+            max_y: 10.0,
+        };
+        let grid = NavigationGrid::new(&bounds, 1.0, |cell| {
+            world_collision_mesh.is_overlapping(cell)
+        });
+
+        let (start, goal) = (glm::vec2(0.5, 0.5), glm::vec2(9.5, 0.5));
+        assert!(grid.find_path(start, glm::vec2(5.5, 2.5), 0.0).is_none());
+        let path = grid.find_path(start, goal, 0.0).unwrap();
+        assert!(path.iter().any(|point| point.y > 8.0));
+    }
+}
@@ -0,0 +1,716 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use legion::{
+    world::{Entry, EntryRef},
+    Entity, World,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    collision::{CollisionLayer, CollisionLayers, CollisionMeshIdentifier},
+    *,
+};
+
+///
+/// Named entity templates with default component values, such as "firefly" or "arrow".
+///
+/// An entity description is a table of components, written the same way in prefab files and level files.
+/// If it has a `prefab` key, the components of that prefab are used as defaults,
+/// and the description only needs to give what differs, down to single fields like `ai_random_walk.speed`.
+///
+pub struct Prefabs {
+    prefabs: HashMap<String, toml::value::Table>,
+}
+
+/// Everything that can go wrong while loading a prefab file.
+#[derive(Debug)]
+pub enum PrefabError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    /// A prefab has a component that no entity could be built with.
+    Entity {
+        prefab: String,
+        error: EntityError,
+    },
+}
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefabError::Io(error) => write!(f, "could not read file: {}", error),
+            PrefabError::Parse(error) => write!(f, "could not parse file: {}", error),
+            PrefabError::Entity { prefab, error } => write!(f, "prefab `{}`: {}", prefab, error),
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+/// Everything that can go wrong while building an entity from its description.
+#[derive(Debug)]
+pub enum EntityError {
+    /// A component could not be read from the description.
+    Component {
+        component: String,
+        error: toml::de::Error,
+    },
+    UnknownComponent(String),
+    UnknownCollisionMesh(String),
+    UnknownPrefab(String),
+    /// A state machine refers to a state it doesn't have.
+    UnknownAiState(String),
+    /// A rigid body's mass is 0 or below.
+    InvalidMass(f32),
+    /// A steering entity's max speed is 0 or below.
+    InvalidMaxSpeed(f32),
+}
+
+impl fmt::Display for EntityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EntityError::Component { component, error } => {
+                write!(f, "invalid component `{}`: {}", component, error)
+            }
+            EntityError::UnknownComponent(component) => {
+                write!(f, "unknown component `{}`", component)
+            }
+            EntityError::UnknownCollisionMesh(mesh) => {
+                write!(f, "unknown collision mesh `{}`", mesh)
+            }
+            EntityError::UnknownPrefab(prefab) => write!(f, "unknown prefab `{}`", prefab),
+            EntityError::UnknownAiState(state) => write!(f, "unknown AI state `{}`", state),
+            EntityError::InvalidMass(mass) => {
+                write!(f, "rigid body mass must be above 0, not {}", mass)
+            }
+            EntityError::InvalidMaxSpeed(max_speed) => {
+                write!(f, "steering max speed must be above 0, not {}", max_speed)
+            }
+        }
+    }
+}
+
+impl std::error::Error for EntityError {}
+
+///
+/// Colliders refer to their collision mesh by name in entity descriptions.
+/// Without layers, they are props that collide with everything.
+///
+#[derive(Deserialize, Serialize)]
+struct ColliderDescription {
+    mesh: String,
+    size: f32,
+    #[serde(default = "default_collider_layers")]
+    layers: CollisionLayers,
+    #[serde(default = "default_collides_with")]
+    collides_with: CollisionLayers,
+}
+
+fn default_collider_layers() -> CollisionLayers {
+    CollisionLayer::PROP.into()
+}
+
+fn default_collides_with() -> CollisionLayers {
+    CollisionLayers::ALL
+}
+
+impl Prefabs {
+    ///
+    /// Loads prefabs from a file with one table of components per prefab.
+    /// Every prefab is built once in a scratch world, so mistakes in its components,
+    /// down to those of its AI states, are caught before anything is spawned.
+    ///
+    pub fn load(
+        path: impl AsRef<Path>,
+        collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+    ) -> Result<Prefabs, PrefabError> {
+        let contents = std::fs::read_to_string(path).map_err(PrefabError::Io)?;
+        let prefabs: HashMap<String, toml::value::Table> =
+            toml::from_str(&contents).map_err(PrefabError::Parse)?;
+        let mut scratch_world = World::default();
+        for (prefab, components) in prefabs.iter() {
+            let scratch_entity = scratch_world.push(());
+            let mut scratch_entry = scratch_world.entry(scratch_entity).unwrap();
+            add_components(
+                &mut scratch_entry,
+                components.clone(),
+                collision_mesh_identifiers,
+            )
+            .map_err(|error| PrefabError::Entity {
+                prefab: prefab.clone(),
+                error,
+            })?;
+        }
+        Ok(Prefabs { prefabs })
+    }
+
+    ///
+    /// Pushes the entity given by the description into the world.
+    /// Every component read here must also be written by `describe_entity`.
+    /// If the entity can't be built, nothing is left of it in the world.
+    ///
+    pub fn spawn(
+        &self,
+        world: &mut World,
+        description: toml::value::Table,
+        collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+    ) -> Result<Entity, EntityError> {
+        let components = self.resolve(description)?;
+
+        let entity = world.push(());
+        let mut entry = world.entry(entity).unwrap();
+        match add_components(&mut entry, components, collision_mesh_identifiers) {
+            Ok(()) => Ok(entity),
+            Err(error) => {
+                world.remove(entity);
+                Err(error)
+            }
+        }
+    }
+
+    ///
+    /// Pushes the named prefab with its default component values into the world.
+    /// Components can be overridden afterwards through the world entry of the returned entity.
+    ///
+    pub fn spawn_prefab(
+        &self,
+        world: &mut World,
+        prefab: &str,
+        collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+    ) -> Result<Entity, EntityError> {
+        let mut description = toml::value::Table::new();
+        description.insert("prefab".into(), toml::Value::String(prefab.into()));
+        self.spawn(world, description, collision_mesh_identifiers)
+    }
+
+    /// Replaces the `prefab` key of the description with the prefab's components, keeping any overrides.
+    fn resolve(
+        &self,
+        mut description: toml::value::Table,
+    ) -> Result<toml::value::Table, EntityError> {
+        let prefab = match description.remove("prefab") {
+            Some(toml::Value::String(prefab)) => prefab,
+            Some(value) => {
+                return Err(EntityError::Component {
+                    component: "prefab".into(),
+                    error: value.try_into::<String>().unwrap_err(),
+                })
+            }
+            None => return Ok(description),
+        };
+
+        let mut components = self
+            .prefabs
+            .get(&prefab)
+            .cloned()
+            .ok_or(EntityError::UnknownPrefab(prefab))?;
+        merge(&mut components, description);
+        Ok(components)
+    }
+}
+
+/// Adds all the components of the description to the entity, and puts it in its state machine's initial state.
+fn add_components(
+    entry: &mut Entry,
+    components: toml::value::Table,
+    collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+) -> Result<(), EntityError> {
+    for (name, value) in components {
+        add_component(entry, &name, value, collision_mesh_identifiers)?;
+    }
+
+    // Entities saved in a state are already in it, with its components
+    let initial_state = entry
+        .get_component::<AiStateMachine>()
+        .ok()
+        .filter(|state_machine| state_machine.state.is_none())
+        .map(|state_machine| state_machine.initial_state.clone());
+    if let Some(initial_state) = initial_state {
+        enter_ai_state(entry, &initial_state, collision_mesh_identifiers)?;
+    }
+    Ok(())
+}
+
+///
+/// Adds the named component, read from its description, to the entity, replacing any it already has.
+/// Every component added here must also be removed by `remove_component`,
+/// and those that make the entity act on its own must be listed in `BEHAVIOUR_COMPONENTS`.
+///
+fn add_component(
+    entry: &mut Entry,
+    name: &str,
+    value: toml::Value,
+    collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+) -> Result<(), EntityError> {
+    match name {
+        "position" => entry.add_component(component::<Position>(name, value)?),
+        "velocity" => entry.add_component(component::<Velocity>(name, value)?),
+        "asset" => entry.add_component(component::<Asset>(name, value)?),
+        "timed_life" => entry.add_component(component::<TimedLife>(name, value)?),
+        "friction" => entry.add_component(component::<Friction>(name, value)?),
+        "continuous_collision" => {
+            entry.add_component(component::<ContinuousCollision>(name, value)?)
+        }
+        "status" => entry.add_component(component::<Status>(name, value)?),
+        "ai_random_walk" => entry.add_component(component::<AiRandomWalk>(name, value)?),
+        "ai_chase" => entry.add_component(component::<AiChase>(name, value)?),
+        "ai_keep_distance" => entry.add_component(component::<AiKeepDistance>(name, value)?),
+        "ai_patrol" => entry.add_component(component::<AiPatrol>(name, value)?),
+        "ai_follow_path" => entry.add_component(component::<AiFollowPath>(name, value)?),
+        "ai_steering" => entry.add_component(ai_steering(value)?),
+        "ai_shoot" => entry.add_component(component::<AiShoot>(name, value)?),
+        "ai_flee" => entry.add_component(component::<AiFlee>(name, value)?),
+        "rigid_body" => entry.add_component(rigid_body(value)?),
+        "sensor" => entry.add_component(component::<Sensor>(name, value)?),
+        "pickup" => entry.add_component(component::<Pickup>(name, value)?),
+        "lure" => entry.add_component(component::<Lure>(name, value)?),
+        "health" => entry.add_component(component::<Health>(name, value)?),
+        "damage" => entry.add_component(component::<Damage>(name, value)?),
+        "collider" => {
+            let description: ColliderDescription = component(name, value)?;
+            let collision_mesh = *collision_mesh_identifiers
+                .get(&description.mesh)
+                .ok_or(EntityError::UnknownCollisionMesh(description.mesh))?;
+            entry.add_component(Collider {
+                collision_mesh,
+                size: description.size,
+                layers: description.layers,
+                collides_with: description.collides_with,
+            });
+        }
+        "ai_state_machine" => {
+            let state_machine: AiStateMachine = component(name, value)?;
+            check_ai_states(&state_machine, collision_mesh_identifiers)?;
+            entry.add_component(state_machine);
+        }
+        _ => return Err(EntityError::UnknownComponent(name.into())),
+    }
+    Ok(())
+}
+
+/// The components that make an entity act on its own, which it stops doing when it dies.
+const BEHAVIOUR_COMPONENTS: [&str; 9] = [
+    "ai_random_walk",
+    "ai_chase",
+    "ai_keep_distance",
+    "ai_patrol",
+    "ai_follow_path",
+    "ai_steering",
+    "ai_shoot",
+    "ai_flee",
+    "ai_state_machine",
+];
+
+/// Removes all the `BEHAVIOUR_COMPONENTS` from the entity, so it stops acting on its own.
+pub fn remove_behaviour(entry: &mut Entry) {
+    for name in BEHAVIOUR_COMPONENTS {
+        remove_component(entry, name);
+    }
+}
+
+/// Removes the named component from the entity, if it has it.
+fn remove_component(entry: &mut Entry, name: &str) {
+    match name {
+        "position" => entry.remove_component::<Position>(),
+        "velocity" => entry.remove_component::<Velocity>(),
+        "asset" => entry.remove_component::<Asset>(),
+        "timed_life" => entry.remove_component::<TimedLife>(),
+        "friction" => entry.remove_component::<Friction>(),
+        "continuous_collision" => entry.remove_component::<ContinuousCollision>(),
+        "status" => entry.remove_component::<Status>(),
+        "ai_random_walk" => entry.remove_component::<AiRandomWalk>(),
+        "ai_chase" => entry.remove_component::<AiChase>(),
+        "ai_keep_distance" => entry.remove_component::<AiKeepDistance>(),
+        "ai_patrol" => entry.remove_component::<AiPatrol>(),
+        "ai_follow_path" => entry.remove_component::<AiFollowPath>(),
+        "ai_steering" => entry.remove_component::<AiSteering>(),
+        "ai_shoot" => entry.remove_component::<AiShoot>(),
+        "ai_flee" => entry.remove_component::<AiFlee>(),
+        "rigid_body" => entry.remove_component::<RigidBody>(),
+        "sensor" => entry.remove_component::<Sensor>(),
+        "pickup" => entry.remove_component::<Pickup>(),
+        "lure" => entry.remove_component::<Lure>(),
+        "health" => entry.remove_component::<Health>(),
+        "damage" => entry.remove_component::<Damage>(),
+        "collider" => entry.remove_component::<Collider>(),
+        "ai_state_machine" => entry.remove_component::<AiStateMachine>(),
+        _ => {}
+    }
+}
+
+///
+/// Checks that the states of the state machine only change to states it has,
+/// and that their components can be added to an entity, so mistakes show up when the entity is spawned.
+///
+fn check_ai_states(
+    state_machine: &AiStateMachine,
+    collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+) -> Result<(), EntityError> {
+    let mut scratch_world = World::default();
+    let scratch_entity = scratch_world.push(());
+    let mut scratch_entry = scratch_world.entry(scratch_entity).unwrap();
+    let states = std::iter::once(&state_machine.initial_state).chain(
+        state_machine
+            .states
+            .values()
+            .flat_map(|state| state.transitions.iter().map(|transition| &transition.to)),
+    );
+    for state in states {
+        if !state_machine.states.contains_key(state) {
+            return Err(EntityError::UnknownAiState(state.clone()));
+        }
+    }
+    for state in state_machine.states.values() {
+        for (name, value) in state.components.iter() {
+            add_component(
+                &mut scratch_entry,
+                name,
+                value.clone(),
+                collision_mesh_identifiers,
+            )?;
+        }
+    }
+    Ok(())
+}
+
+///
+/// Moves the entity's `AiStateMachine` to the state, swapping the components of the state it leaves
+/// for those of the state it enters. Entities without a state machine are left alone.
+///
+pub fn enter_ai_state(
+    entry: &mut Entry,
+    state: &str,
+    collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+) -> Result<(), EntityError> {
+    let mut state_machine = match entry.get_component::<AiStateMachine>() {
+        Ok(state_machine) => state_machine.clone(),
+        Err(_) => return Ok(()),
+    };
+    let entered = state_machine
+        .states
+        .get(state)
+        .cloned()
+        .ok_or_else(|| EntityError::UnknownAiState(state.into()))?;
+
+    if let Some(left) = state_machine.current_state() {
+        for name in left.components.keys() {
+            remove_component(entry, name);
+        }
+    }
+    for (name, value) in entered.components {
+        add_component(entry, &name, value, collision_mesh_identifiers)?;
+    }
+    state_machine.blackboard.extend(entered.set);
+    for (key, amount) in entered.add {
+        *state_machine.blackboard.entry(key).or_default() += amount;
+    }
+    state_machine.state = Some(state.into());
+    state_machine.seconds_in_state = 0.0;
+    entry.add_component(state_machine);
+    Ok(())
+}
+
+///
+/// Describes all components of an entity, so that spawning the description gives back an identical entity.
+///
+pub fn describe_entity(
+    entry: &EntryRef,
+    collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+) -> toml::value::Table {
+    let mut description = toml::value::Table::new();
+    let mut describe = |name: &str, value: Option<toml::Value>| {
+        if let Some(value) = value {
+            description.insert(name.into(), value);
+        }
+    };
+
+    describe("position", value(entry.get_component::<Position>()));
+    describe("velocity", value(entry.get_component::<Velocity>()));
+    describe("asset", value(entry.get_component::<Asset>()));
+    describe("timed_life", value(entry.get_component::<TimedLife>()));
+    describe("friction", value(entry.get_component::<Friction>()));
+    describe(
+        "continuous_collision",
+        value(entry.get_component::<ContinuousCollision>()),
+    );
+    describe("status", value(entry.get_component::<Status>()));
+    describe(
+        "ai_random_walk",
+        value(entry.get_component::<AiRandomWalk>()),
+    );
+    describe("ai_chase", value(entry.get_component::<AiChase>()));
+    describe(
+        "ai_keep_distance",
+        value(entry.get_component::<AiKeepDistance>()),
+    );
+    describe("ai_patrol", value(entry.get_component::<AiPatrol>()));
+    describe(
+        "ai_follow_path",
+        value(entry.get_component::<AiFollowPath>()),
+    );
+    describe("ai_steering", value(entry.get_component::<AiSteering>()));
+    describe("ai_shoot", value(entry.get_component::<AiShoot>()));
+    describe("ai_flee", value(entry.get_component::<AiFlee>()));
+    describe("rigid_body", value(entry.get_component::<RigidBody>()));
+    describe("sensor", value(entry.get_component::<Sensor>()));
+    describe("pickup", value(entry.get_component::<Pickup>()));
+    describe("lure", value(entry.get_component::<Lure>()));
+    describe("health", value(entry.get_component::<Health>()));
+    describe("damage", value(entry.get_component::<Damage>()));
+    describe(
+        "ai_state_machine",
+        value(entry.get_component::<AiStateMachine>()),
+    );
+    describe(
+        "collider",
+        entry.get_component::<Collider>().ok().map(|collider| {
+            let mesh = collision_mesh_identifiers
+                .iter()
+                .find(|(_, identifier)| **identifier == collider.collision_mesh)
+                .map(|(name, _)| name.clone())
+                .expect("Collider uses a collision mesh without a name!");
+            toml::Value::try_from(ColliderDescription {
+                mesh,
+                size: collider.size,
+                layers: collider.layers,
+                collides_with: collider.collides_with,
+            })
+            .unwrap()
+        }),
+    );
+
+    description
+}
+
+fn value<T: Serialize, E>(component: Result<&T, E>) -> Option<toml::Value> {
+    component
+        .ok()
+        .map(|component| toml::Value::try_from(component).unwrap())
+}
+
+/// Recursively overwrites the values of `base` with those in `overrides`.
+fn merge(base: &mut toml::value::Table, overrides: toml::value::Table) {
+    for (key, value) in overrides {
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base)), toml::Value::Table(overrides)) => {
+                merge(base, overrides)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn component<T: DeserializeOwned>(name: &str, value: toml::Value) -> Result<T, EntityError> {
+    value.try_into().map_err(|error| EntityError::Component {
+        component: name.into(),
+        error,
+    })
+}
+
+/// Reads a `RigidBody`, which needs a mass above 0 to be pushed around by.
+fn rigid_body(value: toml::Value) -> Result<RigidBody, EntityError> {
+    let rigid_body: RigidBody = component("rigid_body", value)?;
+    if rigid_body.mass > 0.0 {
+        Ok(rigid_body)
+    } else {
+        Err(EntityError::InvalidMass(rigid_body.mass))
+    }
+}
+
+/// Reads an `AiSteering`, which needs a max speed above 0 to scale its acceleration by.
+fn ai_steering(value: toml::Value) -> Result<AiSteering, EntityError> {
+    let ai_steering: AiSteering = component("ai_steering", value)?;
+    if ai_steering.max_speed > 0.0 {
+        Ok(ai_steering)
+    } else {
+        Err(EntityError::InvalidMaxSpeed(ai_steering.max_speed))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use legion::{EntityStore, Resources, Schedule};
+
+    use super::*;
+
+    /// A wisp stalker that drifts until the player comes close, then chases them, resting every 3 seconds.
+    const STALKER: &str = r#"
+        [stalker]
+        position = { x = 0.0, y = 0.0 }
+
+        [stalker.ai_state_machine]
+        initial_state = "drift"
+
+        [stalker.ai_state_machine.states.drift]
+        components = { ai_steering = { max_speed = 80.0, max_acceleration = 320.0 } }
+        transitions = [{ to = "chase", when = [{ condition = "PlayerCloserThan", distance = 480.0 }] }]
+
+        [stalker.ai_state_machine.states.chase]
+        components = { ai_chase = { speed = 400.0, sight_range = 720.0 } }
+        add = { chases = 1.0 }
+        transitions = [
+            { to = "drift", when = [{ condition = "PlayerFurtherThan", distance = 720.0 }] },
+            { to = "rest", when = [{ condition = "SecondsInStateOver", seconds = 3.0 }] },
+        ]
+
+        [stalker.ai_state_machine.states.rest]
+        transitions = [{ to = "chase", when = [{ condition = "SecondsInStateOver", seconds = 1.5 }] }]
+    "#;
+
+    /// Loads prefabs from a file with the contents, without any collision meshes.
+    fn load(name: &str, contents: &str) -> Result<Prefabs, PrefabError> {
+        let path = std::env::temp_dir().join(format!(
+            "a_little_guy_{}_{}_prefab.toml",
+            std::process::id(),
+            name
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let result = Prefabs::load(&path, &HashMap::new());
+        std::fs::remove_file(path).unwrap();
+        result
+    }
+
+    fn entity_error(result: Result<Prefabs, PrefabError>) -> EntityError {
+        match result {
+            Err(PrefabError::Entity { error, .. }) => error,
+            _ => panic!("The prefabs were loaded"),
+        }
+    }
+
+    #[test]
+    fn rigid_bodies_without_mass_are_rejected() {
+        assert!(matches!(
+            entity_error(load("massless", "[rock.rigid_body]\nmass = 0.0\n")),
+            EntityError::InvalidMass(_)
+        ));
+
+        let prefabs = Prefabs {
+            prefabs: HashMap::new(),
+        };
+        let mut world = World::default();
+        let description =
+            toml::from_str("position = { x = 0.0, y = 0.0 }\nrigid_body = { mass = -1.0 }")
+                .unwrap();
+        let result = prefabs.spawn(&mut world, description, &HashMap::new());
+        assert!(matches!(result, Err(EntityError::InvalidMass(_))));
+        assert_eq!(world.len(), 0);
+    }
+
+    #[test]
+    fn steering_without_max_speed_is_rejected() {
+        assert!(matches!(
+            entity_error(load(
+                "motionless",
+                "[wisp.ai_steering]\nmax_speed = 0.0\nmax_acceleration = 100.0\n"
+            )),
+            EntityError::InvalidMaxSpeed(_)
+        ));
+
+        let prefabs = Prefabs {
+            prefabs: HashMap::new(),
+        };
+        let mut world = World::default();
+        let description = toml::from_str(
+            "position = { x = 0.0, y = 0.0 }\nai_steering = { max_speed = -1.0, max_acceleration = 100.0 }",
+        )
+        .unwrap();
+        let result = prefabs.spawn(&mut world, description, &HashMap::new());
+        assert!(matches!(result, Err(EntityError::InvalidMaxSpeed(_))));
+        assert_eq!(world.len(), 0);
+    }
+
+    #[test]
+    fn state_machines_with_mistakes_are_rejected_when_loading() {
+        assert!(load("stalker", STALKER).is_ok());
+        let mistakes = [
+            (r#"initial_state = "drift""#, r#"initial_state = "hide""#),
+            (r#"to = "drift""#, r#"to = "hide""#),
+            (
+                "components = { ai_chase",
+                "components = { wings = {}, ai_chase",
+            ),
+            (
+                "components = { ai_chase",
+                r#"components = { collider = { mesh = "circle", size = 1.0 }, ai_chase"#,
+            ),
+        ];
+        for (index, (from, to)) in mistakes.iter().enumerate() {
+            let error = entity_error(load(
+                &format!("stalker_{}", index),
+                &STALKER.replacen(from, to, 1),
+            ));
+            match (index, error) {
+                (0 | 1, EntityError::UnknownAiState(state)) => assert_eq!(state, "hide"),
+                (2, EntityError::UnknownComponent(component)) => assert_eq!(component, "wings"),
+                (3, EntityError::UnknownCollisionMesh(mesh)) => assert_eq!(mesh, "circle"),
+                (_, error) => panic!("`{}` gave {}", to, error),
+            }
+        }
+    }
+
+    #[test]
+    fn state_machines_swap_components_as_they_change_state() {
+        let prefabs = load("stalker", STALKER).unwrap();
+        let mut world = World::default();
+        let stalker = prefabs
+            .spawn_prefab(&mut world, "stalker", &HashMap::new())
+            .unwrap();
+        let mut resources = Resources::default();
+        resources.insert(PlayerPosition {
+            position: glm::vec2(1000.0, 0.0),
+        });
+        resources.insert(StateChanges::default());
+        let mut schedule = Schedule::builder()
+            .add_system(state_machine_ai_system())
+            .build();
+        // Runs the state machine for a tick, then switches states the way the game does
+        let mut tick = |world: &mut World, resources: &mut Resources, seconds: f32| {
+            resources.insert(Time {
+                elapsed_seconds: seconds,
+            });
+            schedule.execute(world, resources);
+            let state_changes =
+                std::mem::take(&mut resources.get_mut::<StateChanges>().unwrap().changes);
+            for state_change in state_changes {
+                enter_ai_state(
+                    &mut world.entry(state_change.entity).unwrap(),
+                    &state_change.state,
+                    &HashMap::new(),
+                )
+                .unwrap();
+            }
+        };
+        let state = |world: &World| {
+            let entry = world.entry_ref(stalker).unwrap();
+            let state_machine = entry.get_component::<AiStateMachine>().unwrap();
+            (
+                state_machine.state.clone().unwrap(),
+                state_machine.blackboard.get("chases").copied(),
+                entry.get_component::<AiSteering>().is_ok(),
+                entry.get_component::<AiChase>().is_ok(),
+            )
+        };
+        let drifting = |chases| ("drift".to_string(), chases, true, false);
+        let chasing = |chases| ("chase".to_string(), Some(chases), false, true);
+        let resting = |chases| ("rest".to_string(), Some(chases), false, false);
+
+        assert_eq!(state(&world), drifting(None));
+        tick(&mut world, &mut resources, 1.0);
+        assert_eq!(state(&world), drifting(None));
+
+        resources.get_mut::<PlayerPosition>().unwrap().position = glm::vec2(100.0, 0.0);
+        tick(&mut world, &mut resources, 1.0);
+        assert_eq!(state(&world), chasing(1.0));
+        tick(&mut world, &mut resources, 2.0);
+        assert_eq!(state(&world), chasing(1.0));
+        tick(&mut world, &mut resources, 2.0);
+        assert_eq!(state(&world), resting(1.0));
+        tick(&mut world, &mut resources, 2.0);
+        assert_eq!(state(&world), chasing(2.0));
+
+        resources.get_mut::<PlayerPosition>().unwrap().position = glm::vec2(1000.0, 0.0);
+        tick(&mut world, &mut resources, 1.0);
+        assert_eq!(state(&world), drifting(Some(2.0)));
+    }
+}
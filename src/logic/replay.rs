@@ -0,0 +1,172 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    logic::DEFAULT_TICKS_PER_SECOND,
+    state_input_event::{InputEventHistory, StateInputEvent},
+};
+
+/// Everything that can go wrong while recording or loading a replay.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(error) => write!(f, "could not access replay file: {}", error),
+            ReplayError::Parse(error) => write!(f, "could not parse replay file: {}", error),
+            ReplayError::Serialize(error) => write!(f, "could not write replay file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+///
+/// The layout of a replay file: the seed of the game's random number generator and its tick rate,
+/// followed by the input events of every tick that had any.
+///
+/// Ticks are appended to the file one at a time while recording,
+/// so the replay is complete even if the game is closed without warning.
+///
+#[derive(Serialize, Deserialize)]
+struct ReplayFile {
+    /// TOML integers are signed, so the seed is stored with the same bits as an `i64`.
+    seed: i64,
+    #[serde(default = "default_ticks_per_second")]
+    ticks_per_second: f32,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    ticks: Vec<TickEvents>,
+}
+
+/// Replays recorded before the tick rate was configurable ran at the default rate.
+fn default_ticks_per_second() -> f32 {
+    DEFAULT_TICKS_PER_SECOND
+}
+
+#[derive(Serialize, Deserialize)]
+struct TickEvents {
+    tick: usize,
+    events: Vec<StateInputEvent>,
+}
+
+///
+/// A recorded game that can be played back.
+/// Starting the same level with the same seed and tick rate and feeding it the history reproduces the recorded game.
+///
+pub struct Replay {
+    pub seed: u64,
+    pub ticks_per_second: f32,
+    pub history: InputEventHistory,
+}
+
+impl Replay {
+    pub fn load(path: impl AsRef<Path>) -> Result<Replay, ReplayError> {
+        let contents = std::fs::read_to_string(path).map_err(ReplayError::Io)?;
+        let file: ReplayFile = toml::from_str(&contents).map_err(ReplayError::Parse)?;
+
+        let mut history = InputEventHistory::new();
+        for tick_events in file.ticks {
+            while history.cur_tick_num() < tick_events.tick {
+                history.receive_tick_events(Vec::new());
+            }
+            history.receive_tick_events(tick_events.events);
+        }
+
+        Ok(Replay {
+            seed: file.seed as u64,
+            ticks_per_second: file.ticks_per_second,
+            history,
+        })
+    }
+}
+
+/// Writes the input events of every tick to a replay file as the game runs.
+pub struct ReplayRecorder {
+    writer: BufWriter<File>,
+    tick: usize,
+}
+
+impl ReplayRecorder {
+    pub fn create(
+        path: impl AsRef<Path>,
+        seed: u64,
+        ticks_per_second: f32,
+    ) -> Result<ReplayRecorder, ReplayError> {
+        if let Some(directory) = path.as_ref().parent() {
+            std::fs::create_dir_all(directory).map_err(ReplayError::Io)?;
+        }
+        let mut recorder = ReplayRecorder {
+            writer: BufWriter::new(File::create(path).map_err(ReplayError::Io)?),
+            tick: 0,
+        };
+        recorder.append(&ReplayFile {
+            seed: seed as i64,
+            ticks_per_second,
+            ticks: Vec::new(),
+        })?;
+        Ok(recorder)
+    }
+
+    /// Records the events of the next tick.
+    pub fn record_tick(&mut self, events: &[StateInputEvent]) -> Result<(), ReplayError> {
+        if !events.is_empty() {
+            #[derive(Serialize)]
+            struct AppendedTicks {
+                ticks: [TickEvents; 1],
+            }
+            self.append(&AppendedTicks {
+                ticks: [TickEvents {
+                    tick: self.tick,
+                    events: events.to_vec(),
+                }],
+            })?;
+        }
+        self.tick += 1;
+        Ok(())
+    }
+
+    fn append(&mut self, value: &impl Serialize) -> Result<(), ReplayError> {
+        let contents = toml::to_string(value).map_err(ReplayError::Serialize)?;
+        writeln!(self.writer, "{}", contents).map_err(ReplayError::Io)?;
+        self.writer.flush().map_err(ReplayError::Io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recorded_ticks_and_seed_load_back() {
+        let path =
+            std::env::temp_dir().join(format!("a_little_guy_{}_seed.toml", std::process::id()));
+        // Above `i64::MAX`, the largest integer TOML can hold
+        let seed = u64::MAX - 1;
+        let mut recorder = ReplayRecorder::create(&path, seed, 60.0).unwrap();
+        recorder.record_tick(&[]).unwrap();
+        recorder.record_tick(&[StateInputEvent::Jump]).unwrap();
+        recorder.record_tick(&[]).unwrap();
+        drop(recorder);
+
+        let replay = Replay::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        assert_eq!(replay.seed, seed);
+        assert_eq!(replay.ticks_per_second, 60.0);
+        assert_eq!(replay.history.get_events(0).unwrap().len(), 0);
+        assert!(matches!(
+            replay.history.get_events(1).unwrap(),
+            [StateInputEvent::Jump]
+        ));
+    }
+}
@@ -1,3 +1,178 @@
+use std::collections::HashSet;
+
+use glm::Vec2;
+use legion::Entity;
+
+use super::components::{Position, Velocity};
+
 pub struct Time {
     pub elapsed_seconds: f32,
 }
+
+/// Where the player is at the start of the tick, for the AI to chase, avoid or shoot at.
+pub struct PlayerPosition {
+    pub position: Vec2,
+}
+
+/// A projectile that a system wants spawned from the named prefab.
+pub struct Shot {
+    pub shooter: Entity,
+    pub projectile: String,
+    pub position: Position,
+    pub velocity: Velocity,
+}
+
+/// Shots fired by systems during a tick, spawned by the game once the systems have run.
+#[derive(Default)]
+pub struct Shots {
+    pub shots: Vec<Shot>,
+}
+
+/// A state that a system wants an entity's `AiStateMachine` to change to.
+pub struct StateChange {
+    pub entity: Entity,
+    pub state: String,
+}
+
+/// State changes asked for by systems during a tick, made by the game once the systems have run.
+#[derive(Default)]
+pub struct StateChanges {
+    pub changes: Vec<StateChange>,
+}
+
+///
+/// One entity touching another entity, or touching the world collision mesh if there is no other entity.
+/// Contacts go both ways: when two entities touch, each of them has a contact with the other.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Contact {
+    pub entity: Entity,
+    pub other: Option<Entity>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContactPhase {
+    /// The contact started this tick.
+    Begin,
+    /// The contact started in an earlier tick and still goes on.
+    Stay,
+    /// The contact ended this tick. The entities may have been removed in the meantime.
+    End,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CollisionEvent {
+    pub contact: Contact,
+    pub phase: ContactPhase,
+}
+
+///
+/// The collision events of the current tick, for systems to react to contacts
+/// after collisions have been found and before they are resolved.
+///
+#[derive(Default)]
+pub struct CollisionEvents {
+    pub events: Vec<CollisionEvent>,
+    /// The contacts of the current tick, to tell which contacts begin and end in the next one.
+    contacts: Vec<Contact>,
+}
+
+impl CollisionEvents {
+    /// Replaces the events with those for the contacts of a new tick.
+    pub fn update(&mut self, contacts: Vec<Contact>) {
+        let previous_contacts = std::mem::take(&mut self.contacts);
+        let previous_contact_set: HashSet<Contact> = previous_contacts.iter().copied().collect();
+        let mut contact_set = HashSet::new();
+
+        self.events.clear();
+        for contact in contacts {
+            if contact_set.insert(contact) {
+                self.contacts.push(contact);
+                self.events.push(CollisionEvent {
+                    contact,
+                    phase: if previous_contact_set.contains(&contact) {
+                        ContactPhase::Stay
+                    } else {
+                        ContactPhase::Begin
+                    },
+                });
+            }
+        }
+        for contact in previous_contacts {
+            if !contact_set.contains(&contact) {
+                self.events.push(CollisionEvent {
+                    contact,
+                    phase: ContactPhase::End,
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use legion::World;
+
+    use super::*;
+
+    fn phases(collision_events: &CollisionEvents) -> Vec<(Contact, ContactPhase)> {
+        collision_events
+            .events
+            .iter()
+            .map(|event| (event.contact, event.phase))
+            .collect()
+    }
+
+    #[test]
+    fn contacts_begin_stay_and_end() {
+        let mut world = World::default();
+        let entity = world.push(());
+        let other = world.push(());
+        let with_other = Contact {
+            entity,
+            other: Some(other),
+        };
+        let with_world = Contact {
+            entity,
+            other: None,
+        };
+        let mut collision_events = CollisionEvents::default();
+
+        // Contacts found twice in the same tick give a single event
+        collision_events.update(vec![with_other, with_other]);
+        assert_eq!(
+            phases(&collision_events),
+            [(with_other, ContactPhase::Begin)]
+        );
+
+        collision_events.update(vec![with_other, with_world]);
+        assert_eq!(
+            phases(&collision_events),
+            [
+                (with_other, ContactPhase::Stay),
+                (with_world, ContactPhase::Begin)
+            ]
+        );
+
+        collision_events.update(vec![with_world]);
+        assert_eq!(
+            phases(&collision_events),
+            [
+                (with_world, ContactPhase::Stay),
+                (with_other, ContactPhase::End)
+            ]
+        );
+
+        collision_events.update(vec![]);
+        assert_eq!(phases(&collision_events), [(with_world, ContactPhase::End)]);
+
+        // A contact ending doesn't end again, and touching again begins anew
+        collision_events.update(vec![]);
+        assert!(collision_events.events.is_empty());
+        collision_events.update(vec![with_other]);
+        assert_eq!(
+            phases(&collision_events),
+            [(with_other, ContactPhase::Begin)]
+        );
+    }
+}
@@ -0,0 +1,214 @@
+use std::{collections::HashMap, fmt, path::Path};
+
+use legion::{Entity, EntityStore, IntoQuery, World};
+use rand::SeedableRng;
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    collision::CollisionMeshIdentifier,
+    game::ExtraInfo,
+    prefab::{describe_entity, EntityError, Prefabs},
+};
+
+/// The version of the save format written by this build.
+/// Bump it whenever the format changes, and teach `migrate` how to upgrade the previous version.
+pub const SAVE_VERSION: u32 = 2;
+
+/// Everything that can go wrong while saving or loading the game.
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Parse(toml::de::Error),
+    Serialize(toml::ser::Error),
+    MissingVersion,
+    /// The save was written by a version of the game that can neither be read nor migrated.
+    UnsupportedVersion(u32),
+    /// An entity of the save could not be rebuilt.
+    Entity {
+        entity: usize,
+        error: EntityError,
+    },
+    MissingPlayer(usize),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "could not access save file: {}", error),
+            SaveError::Parse(error) => write!(f, "could not parse save file: {}", error),
+            SaveError::Serialize(error) => write!(f, "could not write save file: {}", error),
+            SaveError::MissingVersion => write!(f, "save file has no version"),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "save file version {} is not supported, expected at most {}",
+                version, SAVE_VERSION
+            ),
+            SaveError::Entity { entity, error } => write!(f, "entity #{}: {}", entity, error),
+            SaveError::MissingPlayer(player) => {
+                write!(f, "player entity #{} is not in the save", player)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+///
+/// Where the game's random number generator is in its sequence: the seed it started from,
+/// and how many words it has generated since.
+///
+#[derive(Serialize, Deserialize)]
+struct RngState {
+    seed: [u8; 32],
+    /// An `i64` because TOML integers are signed. The game never gets anywhere near its end.
+    word_pos: i64,
+}
+
+impl RngState {
+    fn capture(rng: &ChaCha12Rng) -> Self {
+        RngState {
+            seed: rng.get_seed(),
+            word_pos: rng.get_word_pos() as i64,
+        }
+    }
+
+    fn restore(&self) -> ChaCha12Rng {
+        let mut rng = ChaCha12Rng::from_seed(self.seed);
+        rng.set_word_pos(self.word_pos as u128);
+        rng
+    }
+}
+
+///
+/// A snapshot of the entire game state.
+/// Entities are stored as entity descriptions, the same way they are written in level files.
+///
+#[derive(Serialize, Deserialize)]
+pub struct SaveGame {
+    version: u32,
+    /// Seconds since the game started, which animation start times are relative to.
+    time: f32,
+    /// Index of the player in `entities`.
+    player: usize,
+    /// The game continues the same way after loading as it did after saving,
+    /// and saving doesn't change how it continues either.
+    rng: RngState,
+    extra_info: ExtraInfo,
+    entities: Vec<toml::value::Table>,
+}
+
+/// The game state rebuilt from a save.
+pub struct RestoredGame {
+    pub world: World,
+    pub player: Entity,
+    pub extra_info: ExtraInfo,
+    pub rng: ChaCha12Rng,
+    pub time: f32,
+}
+
+impl SaveGame {
+    pub fn capture(
+        world: &World,
+        player: Entity,
+        extra_info: &ExtraInfo,
+        rng: &ChaCha12Rng,
+        time: f32,
+        collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+    ) -> SaveGame {
+        let entities: Vec<Entity> = <Entity>::query().iter(world).copied().collect();
+        let player = entities
+            .iter()
+            .position(|entity| *entity == player)
+            .expect("The player has disappeared!");
+
+        SaveGame {
+            version: SAVE_VERSION,
+            time,
+            player,
+            rng: RngState::capture(rng),
+            extra_info: extra_info.clone(),
+            entities: entities
+                .into_iter()
+                .map(|entity| {
+                    describe_entity(
+                        &world.entry_ref(entity).unwrap(),
+                        collision_mesh_identifiers,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    pub fn write(&self, path: impl AsRef<Path>) -> Result<(), SaveError> {
+        let contents = toml::to_string(self).map_err(SaveError::Serialize)?;
+        if let Some(directory) = path.as_ref().parent() {
+            std::fs::create_dir_all(directory).map_err(SaveError::Io)?;
+        }
+        std::fs::write(path, contents).map_err(SaveError::Io)
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<SaveGame, SaveError> {
+        let contents = std::fs::read_to_string(path).map_err(SaveError::Io)?;
+        let mut save: toml::value::Table = toml::from_str(&contents).map_err(SaveError::Parse)?;
+        let version = save
+            .get("version")
+            .and_then(toml::Value::as_integer)
+            .ok_or(SaveError::MissingVersion)?;
+        migrate(&mut save, version as u32)?;
+        toml::Value::Table(save)
+            .try_into()
+            .map_err(SaveError::Parse)
+    }
+
+    pub fn restore(
+        self,
+        prefabs: &Prefabs,
+        collision_mesh_identifiers: &HashMap<String, CollisionMeshIdentifier>,
+    ) -> Result<RestoredGame, SaveError> {
+        let mut world = World::default();
+        let mut entities = Vec::new();
+        for (index, description) in self.entities.into_iter().enumerate() {
+            entities.push(
+                prefabs
+                    .spawn(&mut world, description, collision_mesh_identifiers)
+                    .map_err(|error| SaveError::Entity {
+                        entity: index,
+                        error,
+                    })?,
+            );
+        }
+        let player = *entities
+            .get(self.player)
+            .ok_or(SaveError::MissingPlayer(self.player))?;
+
+        Ok(RestoredGame {
+            world,
+            player,
+            extra_info: self.extra_info,
+            rng: self.rng.restore(),
+            time: self.time,
+        })
+    }
+}
+
+///
+/// Upgrades a save of the given version to `SAVE_VERSION`, one version at a time.
+///
+fn migrate(save: &mut toml::value::Table, version: u32) -> Result<(), SaveError> {
+    match version {
+        1 => {
+            // Version 1 reseeded the random number generator with `rng_seed` when saving and loading
+            let rng_seed = save
+                .remove("rng_seed")
+                .and_then(|rng_seed| rng_seed.as_integer())
+                .unwrap_or_default();
+            let rng = RngState::capture(&ChaCha12Rng::seed_from_u64(rng_seed as u64));
+            save.insert("rng".into(), toml::Value::try_from(rng).unwrap());
+            save.insert("version".into(), toml::Value::Integer(2));
+            migrate(save, 2)
+        }
+        SAVE_VERSION => Ok(()),
+        _ => Err(SaveError::UnsupportedVersion(version)),
+    }
+}
@@ -0,0 +1,8 @@
+/// Represents the event of something happening outside of state that the logic around the state must handle.
+/// Examples are saving and loading the game.
+/// Unlike `StateInputEvent`s, these are never sent into the game world.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SessionEvent {
+    Save,
+    Load,
+}
@@ -9,7 +9,8 @@ use super::Direction;
 /// These events should be abstracted away before-hand.
 /// Stuff like saving and loading should be handled by logic around the state.
 /// These events are only for events to be sent into the game world.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data")]
 pub enum StateInputEvent {
     /// Makes the player move in the direction given in view coordinates.
     MovePlayerRelative {
@@ -19,9 +20,9 @@ pub enum StateInputEvent {
     Shoot(Direction),
     Charge(Direction),
 }
-/*
+
 /// Represents the entire history of input events.
-#[derive(Serialize, Deserialize)]
+#[derive(Default)]
 pub struct InputEventHistory {
     input_events: Vec<Vec<StateInputEvent>>,
 }
@@ -49,14 +50,8 @@ impl InputEventHistory {
         self.input_events.get(tick_num).map(|vec| &vec[..])
     }
 
-    /// Returns the events for the latest tick.
-    pub fn cur_tick_events(&'_ self) -> Option<&'_ [StateInputEvent]> {
-        self.input_events.last().map(|vec| &vec[..])
-    }
-
     /// Returns the current tick number.
     pub fn cur_tick_num(&self) -> usize {
         self.input_events.len()
     }
 }
-*/
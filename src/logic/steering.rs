@@ -0,0 +1,235 @@
+use glm::Vec2;
+
+/// Below this, distances and speeds are too small to have a direction.
+const MIN_LENGTH: f32 = 0.0001;
+
+/// Another steering entity, as seen by the one being steered.
+#[derive(Copy, Clone, Debug)]
+pub struct Neighbour {
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+/// Shortens the vector to the length if it is longer.
+pub fn truncate(vector: Vec2, max_length: f32) -> Vec2 {
+    let length = vector.norm();
+    if length > max_length {
+        vector * (max_length / length)
+    } else {
+        vector
+    }
+}
+
+fn neighbours_within(
+    position: Vec2,
+    neighbours: &[Neighbour],
+    radius: f32,
+) -> impl Iterator<Item = &Neighbour> {
+    neighbours
+        .iter()
+        .filter(move |neighbour| (neighbour.position - position).norm() < radius)
+}
+
+/// The change to the velocity that heads for the target at full speed.
+pub fn seek(position: Vec2, velocity: Vec2, target: Vec2, max_speed: f32) -> Vec2 {
+    let offset = target - position;
+    if offset.norm() < MIN_LENGTH {
+        return Vec2::zeros();
+    }
+    offset.normalize() * max_speed - velocity
+}
+
+/// The change to the velocity that heads for the target, slowing down within the distance to stop on it.
+pub fn arrive(
+    position: Vec2,
+    velocity: Vec2,
+    target: Vec2,
+    max_speed: f32,
+    slowing_distance: f32,
+) -> Vec2 {
+    let offset = target - position;
+    let distance = offset.norm();
+    if distance < MIN_LENGTH {
+        return -velocity;
+    }
+    let speed = max_speed * (distance / slowing_distance.max(MIN_LENGTH)).min(1.0);
+    offset / distance * speed - velocity
+}
+
+///
+/// The change to the velocity that heads for the point at the angle on a circle of the radius,
+/// the distance ahead of the entity. Entities standing still look ahead along the x axis.
+///
+pub fn wander(velocity: Vec2, angle: f32, radius: f32, distance: f32, max_speed: f32) -> Vec2 {
+    let heading = if velocity.norm() < MIN_LENGTH {
+        glm::vec2(1.0, 0.0)
+    } else {
+        velocity.normalize()
+    };
+    let point = heading * distance + glm::vec2(angle.cos(), angle.sin()) * radius;
+    seek(Vec2::zeros(), velocity, point, max_speed)
+}
+
+/// A push away from the neighbours within the radius, harder the closer they are, up to full speed.
+pub fn separation(position: Vec2, neighbours: &[Neighbour], radius: f32, max_speed: f32) -> Vec2 {
+    let push: Vec2 = neighbours_within(position, neighbours, radius)
+        .filter_map(|neighbour| {
+            let offset = position - neighbour.position;
+            let distance = offset.norm();
+            (distance >= MIN_LENGTH).then(|| offset / distance * (1.0 - distance / radius))
+        })
+        .sum();
+    truncate(push, 1.0) * max_speed
+}
+
+/// The change to the velocity that matches the average velocity of the neighbours within the radius.
+pub fn alignment(position: Vec2, velocity: Vec2, neighbours: &[Neighbour], radius: f32) -> Vec2 {
+    let (sum, count) = neighbours_within(position, neighbours, radius)
+        .fold((Vec2::zeros(), 0), |(sum, count), neighbour| {
+            (sum + neighbour.velocity, count + 1)
+        });
+    if count == 0 {
+        return Vec2::zeros();
+    }
+    sum / count as f32 - velocity
+}
+
+/// The change to the velocity that heads for the center of the neighbours within the radius.
+pub fn cohesion(
+    position: Vec2,
+    velocity: Vec2,
+    neighbours: &[Neighbour],
+    radius: f32,
+    max_speed: f32,
+) -> Vec2 {
+    let (sum, count) = neighbours_within(position, neighbours, radius)
+        .fold((Vec2::zeros(), 0), |(sum, count), neighbour| {
+            (sum + neighbour.position, count + 1)
+        });
+    if count == 0 {
+        return Vec2::zeros();
+    }
+    seek(position, velocity, sum / count as f32, max_speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Vec2, expected: Vec2) {
+        assert!(
+            (actual - expected).norm() < 0.001,
+            "{} is not {}",
+            actual,
+            expected
+        );
+    }
+
+    fn neighbour(x: f32, y: f32, velocity: Vec2) -> Neighbour {
+        Neighbour {
+            position: glm::vec2(x, y),
+            velocity,
+        }
+    }
+
+    #[test]
+    fn truncating_only_shortens() {
+        assert_near(truncate(glm::vec2(30.0, 40.0), 5.0), glm::vec2(3.0, 4.0));
+        assert_near(truncate(glm::vec2(3.0, 4.0), 10.0), glm::vec2(3.0, 4.0));
+        assert_near(truncate(Vec2::zeros(), 10.0), Vec2::zeros());
+    }
+
+    #[test]
+    fn seeking_turns_towards_the_target_at_full_speed() {
+        let velocity = glm::vec2(0.0, 10.0);
+        assert_near(
+            seek(Vec2::zeros(), velocity, glm::vec2(100.0, 0.0), 50.0),
+            glm::vec2(50.0, -10.0),
+        );
+        // Standing on the target, there is no way to head for it
+        assert_near(
+            seek(Vec2::zeros(), velocity, Vec2::zeros(), 50.0),
+            Vec2::zeros(),
+        );
+    }
+
+    #[test]
+    fn arriving_slows_down_within_the_slowing_distance() {
+        let target = glm::vec2(100.0, 0.0);
+        assert_near(
+            arrive(glm::vec2(-100.0, 0.0), Vec2::zeros(), target, 50.0, 100.0),
+            glm::vec2(50.0, 0.0),
+        );
+        assert_near(
+            arrive(glm::vec2(50.0, 0.0), Vec2::zeros(), target, 50.0, 100.0),
+            glm::vec2(25.0, 0.0),
+        );
+        // On the target, it stops
+        assert_near(
+            arrive(target, glm::vec2(5.0, 5.0), target, 50.0, 100.0),
+            glm::vec2(-5.0, -5.0),
+        );
+    }
+
+    #[test]
+    fn wandering_heads_for_the_circle_ahead() {
+        // Standing still, the circle is ahead along the x axis
+        assert_near(
+            wander(Vec2::zeros(), 0.0, 10.0, 20.0, 50.0),
+            glm::vec2(50.0, 0.0),
+        );
+        // Otherwise it is ahead along the velocity, here with the point on the left of the circle
+        let velocity = glm::vec2(0.0, 50.0);
+        assert_near(
+            wander(velocity, std::f32::consts::PI, 20.0, 20.0, 50.0),
+            glm::vec2(-1.0, 1.0).normalize() * 50.0 - velocity,
+        );
+    }
+
+    #[test]
+    fn separation_pushes_harder_from_closer_neighbours() {
+        let still = Vec2::zeros();
+        let close = separation(Vec2::zeros(), &[neighbour(10.0, 0.0, still)], 40.0, 100.0);
+        let far = separation(Vec2::zeros(), &[neighbour(30.0, 0.0, still)], 40.0, 100.0);
+        assert_near(close, glm::vec2(-75.0, 0.0));
+        assert_near(far, glm::vec2(-25.0, 0.0));
+        // Neighbours beyond the radius don't count, and crowds push at full speed at most
+        assert_near(
+            separation(Vec2::zeros(), &[neighbour(50.0, 0.0, still)], 40.0, 100.0),
+            Vec2::zeros(),
+        );
+        let crowd = [
+            neighbour(5.0, 0.0, still),
+            neighbour(0.0, 5.0, still),
+            neighbour(5.0, 5.0, still),
+        ];
+        assert!((separation(Vec2::zeros(), &crowd, 40.0, 100.0).norm() - 100.0).abs() < 0.001);
+    }
+
+    #[test]
+    fn alignment_and_cohesion_follow_the_neighbours_in_range() {
+        let neighbours = [
+            neighbour(10.0, 0.0, glm::vec2(20.0, 0.0)),
+            neighbour(30.0, 0.0, glm::vec2(0.0, 20.0)),
+            neighbour(500.0, 0.0, glm::vec2(-100.0, 0.0)),
+        ];
+        let velocity = glm::vec2(5.0, 0.0);
+        assert_near(
+            alignment(Vec2::zeros(), velocity, &neighbours, 100.0),
+            glm::vec2(5.0, 10.0),
+        );
+        assert_near(
+            cohesion(Vec2::zeros(), velocity, &neighbours, 100.0, 50.0),
+            glm::vec2(45.0, 0.0),
+        );
+        // Alone, there is nothing to follow
+        assert_near(
+            alignment(Vec2::zeros(), velocity, &[], 100.0),
+            Vec2::zeros(),
+        );
+        assert_near(
+            cohesion(Vec2::zeros(), velocity, &[], 100.0, 50.0),
+            Vec2::zeros(),
+        );
+    }
+}
@@ -1,11 +1,20 @@
 use super::components::*;
-use super::Time;
-use legion::system;
-use rand::prelude::StdRng;
-use rand::prelude::ThreadRng;
+use super::{
+    collision::WorldSurfaces,
+    navigation::NavigationGrid,
+    steering::{self, Neighbour},
+    CollisionEvents, ContactPhase, PlayerPosition, Shot, Shots, StateChange, StateChanges, Time,
+};
+use glm::Vec2;
+use legion::{
+    query::component, system, systems::CommandBuffer, world::SubWorld, Entity, EntityStore,
+    IntoQuery,
+};
 use rand::Rng;
+use rand_chacha::ChaCha12Rng;
 
 #[system(for_each)]
+#[filter(!component::<ContinuousCollision>())]
 pub fn update_positions(pos: &mut Position, vel: &Velocity, #[resource] time: &Time) {
     pos.x += vel.dx * time.elapsed_seconds;
     pos.y += vel.dy * time.elapsed_seconds;
@@ -13,9 +22,24 @@ pub fn update_positions(pos: &mut Position, vel: &Velocity, #[resource] time: &T
 }
 
 #[system(for_each)]
-pub fn update_velocities(vel: &mut Velocity, _friction: &Friction, #[resource] time: &Time) {
-    vel.dx *= 0.1f32.powf(time.elapsed_seconds);
-    vel.dy *= 0.1f32.powf(time.elapsed_seconds);
+pub fn update_velocities(
+    vel: &mut Velocity,
+    position: &Position,
+    friction: &Friction,
+    #[resource] world_surfaces: &WorldSurfaces,
+    #[resource] time: &Time,
+) {
+    let (drag_scale, friction_scale) = world_surfaces
+        .material_at(Vec2::from(*position))
+        .friction_scales();
+    let velocity =
+        Vec2::from(*vel) * (-friction.linear_drag * drag_scale * time.elapsed_seconds).exp();
+    let speed = velocity.norm();
+    if speed > 0.0 {
+        let slowed_speed =
+            (speed - friction.ground_friction * friction_scale * time.elapsed_seconds).max(0.0);
+        *vel = Velocity::from(velocity * (slowed_speed / speed));
+    }
 }
 
 #[system(for_each)]
@@ -29,9 +53,826 @@ pub fn random_walk_ai(
     position : &Position,
     ai_random_walk: &AiRandomWalk,
     #[resource] time: &Time,
-    #[resource] rng: &mut StdRng,
+    #[resource] rng: &mut ChaCha12Rng,
 ) {
     let center_dir = ai_random_walk.center - glm::Vec2::from(*position);
     velocity.dx += rng.gen_range(-1.0..1.0) * time.elapsed_seconds * ai_random_walk.speed + center_dir.x * ai_random_walk.centering_speed;
     velocity.dy += rng.gen_range(-1.0..1.0) * time.elapsed_seconds * ai_random_walk.speed + center_dir.y * ai_random_walk.centering_speed;
 }
+
+///
+/// Counts how long entities with an `AiStateMachine` have been in their state,
+/// and asks for the first transition out of it whose conditions all hold.
+///
+#[system(for_each)]
+pub fn state_machine_ai(
+    entity: &Entity,
+    position: &Position,
+    state_machine: &mut AiStateMachine,
+    health: Option<&Health>,
+    #[resource] player: &PlayerPosition,
+    #[resource] time: &Time,
+    #[resource] state_changes: &mut StateChanges,
+) {
+    state_machine.seconds_in_state += time.elapsed_seconds;
+    let player_distance = (player.position - Vec2::from(*position)).norm();
+    let transition = state_machine.current_state().and_then(|state| {
+        state.transitions.iter().find(|transition| {
+            transition
+                .when
+                .iter()
+                .all(|condition| condition.holds(state_machine, player_distance, health))
+        })
+    });
+    if let Some(transition) = transition {
+        state_changes.changes.push(StateChange {
+            entity: *entity,
+            state: transition.to.clone(),
+        });
+    }
+}
+
+///
+/// Steers entities with `AiSteering` by their behaviours.
+/// They see each other as they were at the start of the tick, so the order they are steered in doesn't matter.
+///
+#[system]
+#[read_component(Lure)]
+#[read_component(Position)]
+#[write_component(Velocity)]
+#[write_component(AiSteering)]
+pub fn steering_ai(
+    world: &mut SubWorld,
+    #[resource] player: &PlayerPosition,
+    #[resource] time: &Time,
+    #[resource] rng: &mut ChaCha12Rng,
+) {
+    let lures: Vec<Vec2> = <&Position>::query()
+        .filter(component::<Lure>())
+        .iter(world)
+        .map(|position| Vec2::from(*position))
+        .collect();
+    let flock: Vec<(Entity, Neighbour)> = <(Entity, &Position, &Velocity)>::query()
+        .filter(component::<AiSteering>())
+        .iter(world)
+        .map(|(entity, position, velocity)| {
+            (
+                *entity,
+                Neighbour {
+                    position: Vec2::from(*position),
+                    velocity: Vec2::from(*velocity),
+                },
+            )
+        })
+        .collect();
+
+    let mut query = <(Entity, &Position, &mut Velocity, &mut AiSteering)>::query();
+    for (entity, position, velocity, ai_steering) in query.iter_mut(world) {
+        let position = Vec2::from(*position);
+        let current_velocity = Vec2::from(*velocity);
+        let max_speed = ai_steering.max_speed;
+        let home = *ai_steering.home.get_or_insert(position);
+        let neighbours: Vec<Neighbour> = flock
+            .iter()
+            .filter(|(other, _)| other != entity)
+            .map(|(_, neighbour)| *neighbour)
+            .collect();
+        let target_in_range = |target: SteeringTarget, range: Option<f32>| {
+            match target {
+                SteeringTarget::PLAYER => Some(player.position),
+                SteeringTarget::HOME => Some(home),
+                SteeringTarget::LURE => lures.iter().copied().min_by(|lure_1, lure_2| {
+                    (lure_1 - position)
+                        .norm_squared()
+                        .total_cmp(&(lure_2 - position).norm_squared())
+                }),
+            }
+            .filter(|target| range.is_none_or(|range| (target - position).norm() <= range))
+        };
+
+        let mut change = Vec2::zeros();
+        for seek in ai_steering.seek.iter() {
+            if let Some(target) = target_in_range(seek.target, seek.range) {
+                change +=
+                    steering::seek(position, current_velocity, target, max_speed) * seek.weight;
+            }
+        }
+        for arrive in ai_steering.arrive.iter() {
+            if let Some(target) = target_in_range(arrive.target, arrive.range) {
+                change += steering::arrive(
+                    position,
+                    current_velocity,
+                    target,
+                    max_speed,
+                    arrive.slowing_distance,
+                ) * arrive.weight;
+            }
+        }
+        if let Some(wander) = ai_steering.wander.as_mut() {
+            wander.angle += rng.gen_range(-1.0..1.0) * wander.jitter * time.elapsed_seconds;
+            change += steering::wander(
+                current_velocity,
+                wander.angle,
+                wander.radius,
+                wander.distance,
+                max_speed,
+            ) * wander.weight;
+        }
+        if let Some(separation) = ai_steering.separation {
+            change += steering::separation(position, &neighbours, separation.radius, max_speed)
+                * separation.weight;
+        }
+        if let Some(alignment) = ai_steering.alignment {
+            change +=
+                steering::alignment(position, current_velocity, &neighbours, alignment.radius)
+                    * alignment.weight;
+        }
+        if let Some(cohesion) = ai_steering.cohesion {
+            change += steering::cohesion(
+                position,
+                current_velocity,
+                &neighbours,
+                cohesion.radius,
+                max_speed,
+            ) * cohesion.weight;
+        }
+
+        let acceleration = steering::truncate(
+            change * (ai_steering.max_acceleration / max_speed),
+            ai_steering.max_acceleration,
+        );
+        velocity.dx += acceleration.x * time.elapsed_seconds;
+        velocity.dy += acceleration.y * time.elapsed_seconds;
+    }
+}
+
+#[system(for_each)]
+pub fn update_invulnerability(health: &mut Health, #[resource] time: &Time) {
+    health.invulnerable_seconds_left =
+        (health.invulnerable_seconds_left - time.elapsed_seconds).max(0.0);
+}
+
+/// How close to its preferred distance an `AiKeepDistance` entity slows down, in pixels.
+const KEEP_DISTANCE_SLACK: f32 = 32.0;
+
+/// How close a patrolling entity has to get to a waypoint before it heads to the next one, in pixels.
+const WAYPOINT_REACHED_DISTANCE: f32 = 16.0;
+
+/// The direction from the position to the target and how far away the target is, unless they are in the same place.
+fn direction_to(position: &Position, target: Vec2) -> Option<(Vec2, f32)> {
+    let offset = target - Vec2::from(*position);
+    let distance = offset.norm();
+    if distance > 0.0001 {
+        Some((offset / distance, distance))
+    } else {
+        None
+    }
+}
+
+fn accelerate(velocity: &mut Velocity, direction: Vec2, speed: f32, time: &Time) {
+    velocity.dx += direction.x * speed * time.elapsed_seconds;
+    velocity.dy += direction.y * speed * time.elapsed_seconds;
+}
+
+/// Heads straight for the player, or walks there around obstacles if the entity follows paths.
+#[system(for_each)]
+#[allow(clippy::too_many_arguments)]
+pub fn chase_ai(
+    velocity: &mut Velocity,
+    position: &Position,
+    chase: &AiChase,
+    follow_path: Option<&mut AiFollowPath>,
+    flee: Option<&AiFlee>,
+    health: Option<&Health>,
+    #[resource] player: &PlayerPosition,
+    #[resource] time: &Time,
+) {
+    let chased = direction_to(position, player.position).filter(|(_, distance)| {
+        *distance <= chase.sight_range && !AiFlee::is_fleeing(flee, health)
+    });
+    match follow_path {
+        Some(follow_path) => {
+            follow_path.go_to(chased.map(|_| player.position), chase.speed);
+        }
+        None => {
+            if let Some((direction, _)) = chased {
+                accelerate(velocity, direction, chase.speed, time);
+            }
+        }
+    }
+}
+
+/// How often entities following a path find it again, to keep up with their destination and obstacles moving.
+const REPATH_SECONDS: f32 = 0.5;
+
+///
+/// Walks entities along the path to their destination, finding it again now and then.
+/// Without a path there, they head straight for the destination.
+///
+#[system(for_each)]
+pub fn follow_path(
+    velocity: &mut Velocity,
+    position: &Position,
+    follow_path: &mut AiFollowPath,
+    #[resource] navigation_grid: &NavigationGrid,
+    #[resource] time: &Time,
+) {
+    let destination = match follow_path.destination {
+        Some(destination) => destination,
+        None => {
+            follow_path.path.clear();
+            return;
+        }
+    };
+    follow_path.repath_seconds_left -= time.elapsed_seconds;
+    if follow_path.repath_seconds_left <= 0.0 {
+        follow_path.path = navigation_grid
+            .find_path(Vec2::from(*position), destination, follow_path.radius)
+            .unwrap_or_else(|| vec![destination]);
+        follow_path.next_waypoint = 0;
+        follow_path.repath_seconds_left = REPATH_SECONDS;
+    }
+
+    while let Some(waypoint) = follow_path.path.get(follow_path.next_waypoint) {
+        match direction_to(position, *waypoint) {
+            Some((direction, distance)) if distance > WAYPOINT_REACHED_DISTANCE => {
+                accelerate(velocity, direction, follow_path.speed, time);
+                break;
+            }
+            _ => follow_path.next_waypoint += 1,
+        }
+    }
+}
+
+#[system(for_each)]
+pub fn keep_distance_ai(
+    velocity: &mut Velocity,
+    position: &Position,
+    keep_distance: &AiKeepDistance,
+    flee: Option<&AiFlee>,
+    health: Option<&Health>,
+    #[resource] player: &PlayerPosition,
+    #[resource] time: &Time,
+) {
+    if AiFlee::is_fleeing(flee, health) {
+        return;
+    }
+    if let Some((direction, distance)) = direction_to(position, player.position) {
+        if distance <= keep_distance.sight_range {
+            // Positive when too far away, negative when too close
+            let approach =
+                ((distance - keep_distance.distance) / KEEP_DISTANCE_SLACK).clamp(-1.0, 1.0);
+            accelerate(velocity, direction, approach * keep_distance.speed, time);
+        }
+    }
+}
+
+#[system(for_each)]
+pub fn patrol_ai(
+    velocity: &mut Velocity,
+    position: &Position,
+    patrol: &mut AiPatrol,
+    flee: Option<&AiFlee>,
+    health: Option<&Health>,
+    #[resource] time: &Time,
+) {
+    if AiFlee::is_fleeing(flee, health) || patrol.waypoints.is_empty() {
+        return;
+    }
+    let waypoint = patrol.waypoints[patrol.next_waypoint % patrol.waypoints.len()];
+    match direction_to(position, waypoint) {
+        Some((direction, distance)) if distance > WAYPOINT_REACHED_DISTANCE => {
+            accelerate(velocity, direction, patrol.speed, time);
+        }
+        _ => patrol.next_waypoint = (patrol.next_waypoint + 1) % patrol.waypoints.len(),
+    }
+}
+
+#[system(for_each)]
+#[allow(clippy::too_many_arguments)]
+pub fn shoot_ai(
+    entity: &Entity,
+    position: &Position,
+    shoot: &mut AiShoot,
+    flee: Option<&AiFlee>,
+    health: Option<&Health>,
+    #[resource] player: &PlayerPosition,
+    #[resource] time: &Time,
+    #[resource] shots: &mut Shots,
+) {
+    shoot.cooldown_seconds_left = (shoot.cooldown_seconds_left - time.elapsed_seconds).max(0.0);
+    if shoot.cooldown_seconds_left > 0.0 || AiFlee::is_fleeing(flee, health) {
+        return;
+    }
+    if let Some((direction, distance)) = direction_to(position, player.position) {
+        if distance <= shoot.range {
+            shots.shots.push(Shot {
+                shooter: *entity,
+                projectile: shoot.projectile.clone(),
+                position: *position,
+                velocity: Velocity::from(direction * shoot.projectile_speed),
+            });
+            shoot.cooldown_seconds_left = shoot.cooldown_seconds;
+        }
+    }
+}
+
+#[system(for_each)]
+pub fn flee_ai(
+    velocity: &mut Velocity,
+    position: &Position,
+    flee: &AiFlee,
+    health: &Health,
+    #[resource] player: &PlayerPosition,
+    #[resource] time: &Time,
+) {
+    if AiFlee::is_fleeing(Some(flee), Some(health)) {
+        if let Some((direction, _)) = direction_to(position, player.position) {
+            accelerate(velocity, -direction, flee.speed, time);
+        }
+    }
+}
+
+///
+/// Takes hit points from entities with `Health` that touch an entity with `Damage` of the opposing team,
+/// unless they are still invulnerable from their last hit.
+/// Damage that is removed on hit only hurts the first entity it touches, even if it touches several in the same tick.
+///
+#[system]
+#[read_component(Damage)]
+#[read_component(Status)]
+#[write_component(Health)]
+pub fn apply_damage(
+    world: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] collision_events: &CollisionEvents,
+) {
+    let mut removed = Vec::new();
+    for event in collision_events.events.iter() {
+        let (hurt, damaging) = match (event.phase, event.contact.other) {
+            (ContactPhase::Begin | ContactPhase::Stay, Some(damaging)) => {
+                (event.contact.entity, damaging)
+            }
+            _ => continue,
+        };
+        if removed.contains(&damaging) {
+            continue;
+        }
+        let (damage, damaging_team) = match world.entry_ref(damaging) {
+            Ok(damaging) => match (
+                damaging.get_component::<Damage>(),
+                damaging.get_component::<Status>(),
+            ) {
+                (Ok(damage), Ok(status)) => (*damage, status.team),
+                _ => continue,
+            },
+            Err(_) => continue,
+        };
+
+        let mut hurt = match world.entry_mut(hurt) {
+            Ok(hurt) => hurt,
+            Err(_) => continue,
+        };
+        match hurt.get_component::<Status>() {
+            Ok(status) if status.team != damaging_team => {}
+            _ => continue,
+        }
+        if let Ok(health) = hurt.get_component_mut::<Health>() {
+            if !health.is_invulnerable() {
+                health.hit_points -= damage.amount;
+                health.invulnerable_seconds_left = health.invulnerability_seconds;
+            }
+            if damage.removed_on_hit {
+                removed.push(damaging);
+                commands.remove(damaging);
+            }
+        }
+    }
+}
+
+///
+/// Heals entities of the player's team that touch a pickup, removing the pickup.
+/// Each pickup is collected once, even if several entities touch it in the same tick.
+///
+#[system]
+#[read_component(Pickup)]
+#[read_component(Status)]
+#[write_component(Health)]
+pub fn collect_pickups(
+    world: &mut SubWorld,
+    commands: &mut CommandBuffer,
+    #[resource] collision_events: &CollisionEvents,
+) {
+    let mut collected = Vec::new();
+    for event in collision_events.events.iter() {
+        let (pickup, collector) = match (event.phase, event.contact.other) {
+            (ContactPhase::Begin | ContactPhase::Stay, Some(collector)) => {
+                (event.contact.entity, collector)
+            }
+            _ => continue,
+        };
+        if collected.contains(&pickup) {
+            continue;
+        }
+        let heal = match world
+            .entry_ref(pickup)
+            .ok()
+            .and_then(|pickup| pickup.get_component::<Pickup>().ok().copied())
+        {
+            Some(pickup) => pickup.heal,
+            None => continue,
+        };
+
+        let mut collector = match world.entry_mut(collector) {
+            Ok(collector) => collector,
+            Err(_) => continue,
+        };
+        if !matches!(collector.get_component::<Status>(), Ok(status) if status.team == Team::PLAYER)
+        {
+            continue;
+        }
+        if let Ok(health) = collector.get_component_mut::<Health>() {
+            health.heal(heal);
+            collected.push(pickup);
+            commands.remove(pickup);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use legion::{Resources, Schedule, World};
+
+    use super::super::{
+        collision::{SurfaceMaterial, Triangle},
+        Contact,
+    };
+    use super::*;
+
+    fn run(
+        system: impl legion::systems::ParallelRunnable + 'static,
+        world: &mut World,
+        resources: &mut Resources,
+    ) {
+        Schedule::builder()
+            .add_system(system)
+            .build()
+            .execute(world, resources);
+    }
+
+    fn health(hit_points: f32) -> Health {
+        Health {
+            hit_points,
+            max_hit_points: None,
+            invulnerability_seconds: 1.0,
+            invulnerable_seconds_left: 0.0,
+            death_animation: None,
+        }
+    }
+
+    fn damage(amount: f32, removed_on_hit: bool) -> Damage {
+        Damage {
+            amount,
+            removed_on_hit,
+        }
+    }
+
+    /// Collision events with the entity touching each of the others, as if they had just started to.
+    fn touching(entity: Entity, others: &[Entity]) -> CollisionEvents {
+        let mut collision_events = CollisionEvents::default();
+        collision_events.update(
+            others
+                .iter()
+                .map(|other| Contact {
+                    entity,
+                    other: Some(*other),
+                })
+                .collect(),
+        );
+        collision_events
+    }
+
+    /// Resources for the AI systems, with the player at the origin.
+    fn ai_resources(elapsed_seconds: f32) -> Resources {
+        let mut resources = Resources::default();
+        resources.insert(PlayerPosition {
+            position: Vec2::zeros(),
+        });
+        resources.insert(Time { elapsed_seconds });
+        resources.insert(Shots::default());
+        resources
+    }
+
+    fn velocity(world: &World, entity: Entity) -> Vec2 {
+        Vec2::from(
+            *world
+                .entry_ref(entity)
+                .unwrap()
+                .get_component::<Velocity>()
+                .unwrap(),
+        )
+    }
+
+    fn still() -> Velocity {
+        Velocity { dx: 0.0, dy: 0.0 }
+    }
+
+    /// Health low enough for an `AiFlee` that flees below 2 hit points.
+    fn fleeing() -> (AiFlee, Health) {
+        (
+            AiFlee {
+                speed: 50.0,
+                below_hit_points: 2.0,
+            },
+            health(1.0),
+        )
+    }
+
+    #[test]
+    fn chasers_head_for_the_player_in_sight() {
+        let chase = AiChase {
+            speed: 50.0,
+            sight_range: 200.0,
+        };
+        let mut world = World::default();
+        let in_sight = world.push((Position { x: 100.0, y: 0.0 }, still(), chase));
+        let out_of_sight = world.push((Position { x: 300.0, y: 0.0 }, still(), chase));
+        let (flee, health) = fleeing();
+        let fleeing = world.push((Position { x: 100.0, y: 0.0 }, still(), chase, flee, health));
+        let mut resources = ai_resources(1.0);
+
+        run(chase_ai_system(), &mut world, &mut resources);
+        assert_eq!(velocity(&world, in_sight), glm::vec2(-50.0, 0.0));
+        assert_eq!(velocity(&world, out_of_sight), Vec2::zeros());
+        assert_eq!(velocity(&world, fleeing), Vec2::zeros());
+    }
+
+    #[test]
+    fn keeping_distance_stops_at_the_distance_and_backs_off_when_too_close() {
+        let keep_distance = AiKeepDistance {
+            speed: 50.0,
+            distance: 100.0,
+            sight_range: 300.0,
+        };
+        let mut world = World::default();
+        let at_distance = world.push((Position { x: 100.0, y: 0.0 }, still(), keep_distance));
+        let at_sight_range = world.push((Position { x: 300.0, y: 0.0 }, still(), keep_distance));
+        let out_of_sight = world.push((Position { x: 301.0, y: 0.0 }, still(), keep_distance));
+        let too_close = world.push((Position { x: 40.0, y: 0.0 }, still(), keep_distance));
+        let mut resources = ai_resources(1.0);
+
+        run(keep_distance_ai_system(), &mut world, &mut resources);
+        assert_eq!(velocity(&world, at_distance), Vec2::zeros());
+        assert_eq!(velocity(&world, at_sight_range), glm::vec2(-50.0, 0.0));
+        assert_eq!(velocity(&world, out_of_sight), Vec2::zeros());
+        assert_eq!(velocity(&world, too_close), glm::vec2(50.0, 0.0));
+    }
+
+    #[test]
+    fn patrols_walk_their_waypoints_in_turn() {
+        let patrol = |waypoints: Vec<Vec2>| AiPatrol {
+            speed: 50.0,
+            waypoints,
+            next_waypoint: 0,
+        };
+        let mut world = World::default();
+        let walking = world.push((
+            Position { x: 0.0, y: 0.0 },
+            still(),
+            patrol(vec![glm::vec2(0.0, 100.0)]),
+        ));
+        let arrived = world.push((
+            Position { x: 0.0, y: 100.0 },
+            still(),
+            patrol(vec![glm::vec2(0.0, 100.0)]),
+        ));
+        let turning = world.push((
+            Position { x: 0.0, y: 100.0 },
+            still(),
+            patrol(vec![glm::vec2(0.0, 100.0), glm::vec2(100.0, 100.0)]),
+        ));
+        let mut resources = ai_resources(1.0);
+        let next_waypoint = |world: &World, entity| {
+            world
+                .entry_ref(entity)
+                .unwrap()
+                .get_component::<AiPatrol>()
+                .unwrap()
+                .next_waypoint
+        };
+
+        run(patrol_ai_system(), &mut world, &mut resources);
+        assert_eq!(velocity(&world, walking), glm::vec2(0.0, 50.0));
+        // With a single waypoint, reaching it just keeps the entity there
+        assert_eq!(velocity(&world, arrived), Vec2::zeros());
+        assert_eq!(next_waypoint(&world, arrived), 0);
+        assert_eq!(next_waypoint(&world, turning), 1);
+
+        run(patrol_ai_system(), &mut world, &mut resources);
+        assert_eq!(velocity(&world, turning), glm::vec2(50.0, 0.0));
+    }
+
+    #[test]
+    fn shooters_wait_for_their_cooldown() {
+        let shoot = AiShoot {
+            projectile: "enemy_arrow".into(),
+            projectile_speed: 400.0,
+            range: 200.0,
+            cooldown_seconds: 1.0,
+            cooldown_seconds_left: 0.5,
+        };
+        let mut world = World::default();
+        let shooter = world.push((Position { x: 0.0, y: 100.0 }, shoot.clone()));
+        world.push((Position { x: 0.0, y: 300.0 }, shoot.clone()));
+        let mut resources = ai_resources(0.25);
+        let cooldown_seconds_left = |world: &World| {
+            world
+                .entry_ref(shooter)
+                .unwrap()
+                .get_component::<AiShoot>()
+                .unwrap()
+                .cooldown_seconds_left
+        };
+
+        run(shoot_ai_system(), &mut world, &mut resources);
+        assert!(resources.get::<Shots>().unwrap().shots.is_empty());
+        assert_eq!(cooldown_seconds_left(&world), 0.25);
+
+        run(shoot_ai_system(), &mut world, &mut resources);
+        let shots = resources.get::<Shots>().unwrap();
+        // The one out of range never shoots
+        assert_eq!(shots.shots.len(), 1);
+        let shot = &shots.shots[0];
+        assert_eq!(shot.shooter, shooter);
+        assert_eq!(shot.projectile, "enemy_arrow");
+        assert_eq!(Vec2::from(shot.velocity), glm::vec2(0.0, -400.0));
+        assert_eq!(cooldown_seconds_left(&world), 1.0);
+    }
+
+    #[test]
+    fn fleeing_runs_away_once_hurt_enough() {
+        let (flee, hurt) = fleeing();
+        let mut world = World::default();
+        let fleeing = world.push((Position { x: 100.0, y: 0.0 }, still(), flee, hurt));
+        let healthy = world.push((Position { x: 100.0, y: 0.0 }, still(), flee, health(3.0)));
+        let mut resources = ai_resources(1.0);
+
+        run(flee_ai_system(), &mut world, &mut resources);
+        assert_eq!(velocity(&world, fleeing), glm::vec2(50.0, 0.0));
+        assert_eq!(velocity(&world, healthy), Vec2::zeros());
+    }
+
+    #[test]
+    fn steering_weighs_the_behaviours_and_caps_the_acceleration() {
+        let steering = |seek: Vec<SeekBehaviour>, separation: Option<FlockBehaviour>| AiSteering {
+            max_speed: 100.0,
+            max_acceleration: 200.0,
+            home: None,
+            seek,
+            arrive: Vec::new(),
+            wander: None,
+            separation,
+            alignment: None,
+            cohesion: None,
+        };
+        let seek_player = |weight: f32| {
+            vec![SeekBehaviour {
+                target: SteeringTarget::PLAYER,
+                weight,
+                range: None,
+            }]
+        };
+        let separation = Some(FlockBehaviour {
+            weight: 0.5,
+            radius: 40.0,
+        });
+        let mut world = World::default();
+        let seeking = world.push((
+            Position { x: 100.0, y: 0.0 },
+            still(),
+            steering(seek_player(0.5), None),
+        ));
+        let eager = world.push((
+            Position { x: 0.0, y: 100.0 },
+            still(),
+            steering(seek_player(4.0), None),
+        ));
+        let separating = world.push((
+            Position { x: 300.0, y: 300.0 },
+            still(),
+            steering(Vec::new(), separation),
+        ));
+        world.push((
+            Position { x: 310.0, y: 300.0 },
+            still(),
+            steering(Vec::new(), separation),
+        ));
+        let mut resources = ai_resources(1.0);
+        resources.insert(<ChaCha12Rng as rand::SeedableRng>::seed_from_u64(0));
+
+        run(steering_ai_system(), &mut world, &mut resources);
+        // Full acceleration is reached when the weighed change is off by the max speed
+        assert_eq!(velocity(&world, seeking), glm::vec2(-100.0, 0.0));
+        assert_eq!(velocity(&world, eager), glm::vec2(0.0, -200.0));
+        // The neighbour 10 away within a radius of 40 pushes at three quarters of the max speed
+        assert_eq!(velocity(&world, separating), glm::vec2(-75.0, 0.0));
+    }
+
+    #[test]
+    fn entities_slow_down_less_on_ice_than_on_mud() {
+        let square = |x: f32, material: SurfaceMaterial| {
+            [
+                (
+                    Triangle::new(
+                        glm::vec2(x, 0.0),
+                        glm::vec2(x + 100.0, 0.0),
+                        glm::vec2(x + 100.0, 100.0),
+                    ),
+                    material,
+                ),
+                (
+                    Triangle::new(
+                        glm::vec2(x, 0.0),
+                        glm::vec2(x + 100.0, 100.0),
+                        glm::vec2(x, 100.0),
+                    ),
+                    material,
+                ),
+            ]
+        };
+        let mut resources = ai_resources(0.5);
+        resources.insert(WorldSurfaces::new(
+            square(0.0, SurfaceMaterial::ICE)
+                .into_iter()
+                .chain(square(200.0, SurfaceMaterial::MUD))
+                .collect(),
+        ));
+        let friction = Friction {
+            linear_drag: 1.0,
+            ground_friction: 10.0,
+        };
+        let sliding = Velocity { dx: 100.0, dy: 0.0 };
+        let mut world = World::default();
+        let on_ice = world.push((Position { x: 50.0, y: 50.0 }, sliding, friction));
+        let on_ground = world.push((Position { x: 150.0, y: 50.0 }, sliding, friction));
+        let on_mud = world.push((Position { x: 250.0, y: 50.0 }, sliding, friction));
+
+        run(update_velocities_system(), &mut world, &mut resources);
+        let speed = |entity| velocity(&world, entity).x;
+        assert!(speed(on_ice) > speed(on_ground));
+        assert!(speed(on_ground) > speed(on_mud));
+        // Drag and ground friction both apply, scaled by the material
+        let expected = |(drag_scale, friction_scale): (f32, f32)| {
+            100.0 * (-0.5 * drag_scale).exp() - 5.0 * friction_scale
+        };
+        assert!((speed(on_ice) - expected(SurfaceMaterial::ICE.friction_scales())).abs() < 0.001);
+        assert!((speed(on_mud) - expected(SurfaceMaterial::MUD.friction_scales())).abs() < 0.001);
+    }
+
+    #[test]
+    fn damage_hurts_the_other_team_and_then_makes_it_invulnerable() {
+        let mut world = World::default();
+        let player = world.push((Status { team: Team::PLAYER }, health(5.0)));
+        let enemy = world.push((Status { team: Team::ENEMY }, damage(2.0, false)));
+        let friend = world.push((Status { team: Team::PLAYER }, damage(2.0, false)));
+        let mut resources = Resources::default();
+        resources.insert(touching(player, &[enemy, friend]));
+        resources.insert(Time {
+            elapsed_seconds: 0.6,
+        });
+        let hit_points = |world: &World| {
+            world
+                .entry_ref(player)
+                .unwrap()
+                .get_component::<Health>()
+                .unwrap()
+                .hit_points
+        };
+
+        run(apply_damage_system(), &mut world, &mut resources);
+        assert_eq!(hit_points(&world), 3.0);
+
+        // Still touching, but the invulnerability frames have only partly run out
+        run(update_invulnerability_system(), &mut world, &mut resources);
+        run(apply_damage_system(), &mut world, &mut resources);
+        assert_eq!(hit_points(&world), 3.0);
+
+        run(update_invulnerability_system(), &mut world, &mut resources);
+        run(apply_damage_system(), &mut world, &mut resources);
+        assert_eq!(hit_points(&world), 1.0);
+    }
+
+    #[test]
+    fn projectiles_are_removed_on_their_first_hit() {
+        let mut world = World::default();
+        let player = world.push((Status { team: Team::PLAYER }, health(5.0)));
+        let arrow = world.push((Status { team: Team::ENEMY }, damage(1.0, true)));
+        let mut resources = Resources::default();
+        resources.insert(touching(player, &[arrow]));
+
+        run(apply_damage_system(), &mut world, &mut resources);
+        assert!(world.entry_ref(arrow).is_err());
+        let player = world.entry_ref(player).unwrap();
+        assert_eq!(player.get_component::<Health>().unwrap().hit_points, 4.0);
+    }
+}
@@ -6,7 +6,71 @@ mod graphics;
 
 use std::sync::mpsc;
 
+/// The level the game starts in.
+const START_LEVEL: &str = "assets/levels/meadow.toml";
+
+///
+/// Reads the command line:
+/// `a_little_guy [LEVEL] [--tick-rate TICKS_PER_SECOND] [--record FILE] [--replay FILE]
+/// [--headless TICKS [--script FILE] [--output FILE]]`
+///
+/// Returns the headless configuration only if the game should run without a window.
+///
+fn config_from_args() -> (logic::LogicConfig, Option<logic::HeadlessConfig>) {
+    let mut headless_ticks = None;
+    let mut script_path = None;
+    let mut output_path = None;
+    let mut config = logic::LogicConfig {
+        level_path: START_LEVEL.into(),
+        ticks_per_second: logic::DEFAULT_TICKS_PER_SECOND,
+        record_path: None,
+        replay_path: None,
+    };
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--tick-rate" => {
+                config.ticks_per_second = args
+                    .next()
+                    .and_then(|rate| rate.parse().ok())
+                    .filter(|rate| *rate > 0.0)
+                    .expect("--tick-rate needs a positive number of ticks per second")
+            }
+            "--record" => {
+                config.record_path = Some(args.next().expect("--record needs a file").into())
+            }
+            "--replay" => {
+                config.replay_path = Some(args.next().expect("--replay needs a file").into())
+            }
+            "--headless" => {
+                headless_ticks = Some(
+                    args.next()
+                        .and_then(|ticks| ticks.parse().ok())
+                        .expect("--headless needs a number of ticks"),
+                )
+            }
+            "--script" => script_path = Some(args.next().expect("--script needs a file").into()),
+            "--output" => output_path = Some(args.next().expect("--output needs a file").into()),
+            _ => config.level_path = arg.into(),
+        }
+    }
+
+    let headless_config = headless_ticks.map(|ticks| logic::HeadlessConfig {
+        ticks,
+        script_path,
+        output_path,
+    });
+    (config, headless_config)
+}
+
 fn main() {
+    let (logic_config, headless_config) = config_from_args();
+
+    if let Some(headless_config) = headless_config {
+        print!("{}", logic::run_headless(logic_config, headless_config));
+        return;
+    }
+
     let (game_event_sender, game_event_receiver) = mpsc::channel();
     let window_to_logic_sender = channels::WindowToLogicSender {
         channel_sender: game_event_sender,
@@ -23,8 +87,11 @@ fn main() {
         render_pack: graphics_receiver,
     };
 
-    let logic_join_handle =
-        logic::start_logic_thread(window_to_logic_receiver, logic_to_window_sender);
+    let logic_join_handle = logic::start_logic_thread(
+        logic_config,
+        window_to_logic_receiver,
+        logic_to_window_sender,
+    );
 
     graphics::start_window(logic_to_window_receiver, window_to_logic_sender);
 